@@ -33,22 +33,22 @@ impl fmt::Display for Report {
     }
 }
 
-pub fn analyze(buffer: Vec<u8>) -> String {
+pub fn analyze(buffer: &[u8]) -> String {
     let length = buffer.len();
     let mut md5 = Md5::new();
     let mut sha1 = Sha1::new();
     let mut sha256 = Sha256::new();
     let mut sha512 = Sha512::new();
 
-    md5.update(&buffer);
-    sha1.update(&buffer);
-    sha256.update(&buffer);
-    sha512.update(&buffer);
+    md5.update(buffer);
+    sha1.update(buffer);
+    sha256.update(buffer);
+    sha512.update(buffer);
 
     let report = Report {
         size: ByteSize::b(length as u64).to_string(),
-        shannon_entropy: shannon_entropy(&buffer),
-        absolute_entropy: normalized_absolute_entropy(&buffer),
+        shannon_entropy: shannon_entropy(buffer),
+        absolute_entropy: normalized_absolute_entropy(buffer),
         md5: encode(md5.finalize()),
         sha1: encode(sha1.finalize()),
         sha256: encode(sha256.finalize()),
@@ -59,7 +59,7 @@ pub fn analyze(buffer: Vec<u8>) -> String {
 }
 
 /// Calculate the Shannon entropy.
-fn shannon_entropy(buffer: &[u8]) -> f64 {
+pub fn shannon_entropy(buffer: &[u8]) -> f64 {
     let length = buffer.len();
     let mut entropy = 0.0_f64;
     let mut counts = [0_u64; 256];
@@ -103,7 +103,7 @@ mod tests {
     fn equal_distribution_has_full_shannon_entropy() {
         let mut bytes = [0_u8; 256];
 
-        for index in 0..256 { bytes[index] = index as u8; }
+        for (index, byte) in bytes.iter_mut().enumerate() { *byte = index as u8; }
 
         assert_eq!(shannon_entropy(&bytes), 8.0);
     }
@@ -117,7 +117,7 @@ mod tests {
     fn equal_distribution_has_full_absolute_entropy() {
         let mut bytes = [0_u8; 256];
 
-        for index in 0..256 { bytes[index] = index as u8; }
+        for (index, byte) in bytes.iter_mut().enumerate() { *byte = index as u8; }
 
         assert_eq!(normalized_absolute_entropy(&bytes), 256.0);
     }
@@ -0,0 +1,221 @@
+use clap::ValueEnum;
+
+/// The multiplication table of the dihedral group D5, used by the Verhoeff algorithm.
+const VERHOEFF_MULTIPLICATION: [[u8; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0]
+];
+
+/// The permutation table used by the Verhoeff algorithm.
+const VERHOEFF_PERMUTATION: [[u8; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8]
+];
+
+/// The multiplicative inverses used by the Verhoeff algorithm.
+const VERHOEFF_INVERSE: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
+
+/// The totally anti-symmetric quasigroup used by the Damm algorithm.
+const DAMM_QUASIGROUP: [[u8; 10]; 10] = [
+    [0, 3, 1, 7, 5, 9, 8, 6, 4, 2],
+    [7, 0, 9, 2, 1, 5, 4, 8, 6, 3],
+    [4, 2, 0, 6, 8, 7, 1, 3, 5, 9],
+    [1, 7, 5, 0, 9, 8, 3, 4, 2, 6],
+    [6, 1, 2, 3, 0, 4, 5, 9, 7, 8],
+    [3, 6, 7, 4, 2, 0, 9, 5, 8, 1],
+    [5, 8, 6, 9, 7, 2, 0, 1, 3, 4],
+    [8, 9, 4, 5, 3, 6, 2, 0, 1, 7],
+    [9, 4, 3, 8, 6, 1, 7, 2, 0, 5],
+    [2, 5, 8, 1, 4, 3, 6, 7, 9, 0]
+];
+
+/// An algorithm that protects a sequence of digits against transcription errors.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Checksum {
+    /// The Luhn algorithm, as used by payment cards and IMEIs
+    Luhn,
+    /// The Verhoeff algorithm, which detects all single-digit errors and adjacent transpositions
+    Verhoeff,
+    /// The Damm algorithm, which detects all single-digit errors and adjacent transpositions
+    Damm,
+    /// ISO 7064 MOD 97-10, as used by IBANs
+    Mod97
+}
+
+impl Checksum {
+    /// The number of check digits that are appended by this algorithm.
+    pub fn length(&self) -> usize {
+        match self {
+            Checksum::Mod97 => 2,
+            _ => 1
+        }
+    }
+
+    /// Calculate the check digits for a sequence of ASCII digits.
+    pub fn compute(&self, digits: &[u8]) -> Vec<u8> {
+        let values = to_values(digits);
+
+        match self {
+            Checksum::Luhn => vec![luhn(&values, true)],
+            Checksum::Verhoeff => vec![verhoeff(&values, true)],
+            Checksum::Damm => vec![damm(&values)],
+            Checksum::Mod97 => {
+                let remainder = mod97(&values) * 100 % 97;
+                let check = 98 - remainder;
+
+                vec![(check / 10) as u8, (check % 10) as u8]
+            }
+        }
+        .into_iter()
+        .map(|value| value + b'0')
+        .collect()
+    }
+
+    /// Check whether a sequence of ASCII digits ends with valid check digits.
+    pub fn validate(&self, digits: &[u8]) -> bool {
+        if digits.len() <= self.length() || !digits.iter().all(u8::is_ascii_digit) { return false; }
+
+        let values = to_values(digits);
+
+        match self {
+            Checksum::Luhn => luhn(&values, false) == 0,
+            Checksum::Verhoeff => verhoeff(&values, false) == 0,
+            Checksum::Damm => damm(&values) == 0,
+            Checksum::Mod97 => mod97(&values) == 1
+        }
+    }
+}
+
+fn to_values(digits: &[u8]) -> Vec<u8> {
+    digits.iter().map(|digit| digit - b'0').collect()
+}
+
+/// Calculate the Luhn check digit, or the Luhn remainder if `compute` is false.
+fn luhn(values: &[u8], compute: bool) -> u8 {
+    let offset = if compute { 0 } else { 1 };
+    let sum: u32 = values
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, value)| {
+            let value = *value as u32;
+
+            if index % 2 == offset {
+                let doubled = value * 2;
+
+                if doubled > 9 { doubled - 9 } else { doubled }
+            }
+            else { value }
+        })
+        .sum();
+
+    if compute { ((10 - sum % 10) % 10) as u8 }
+    else { (sum % 10) as u8 }
+}
+
+/// Calculate the Verhoeff check digit, or the Verhoeff checksum if `compute` is false.
+fn verhoeff(values: &[u8], compute: bool) -> u8 {
+    let offset = if compute { 1 } else { 0 };
+    let checksum = values
+        .iter()
+        .rev()
+        .enumerate()
+        .fold(0, |checksum, (index, value)| {
+            let permuted = VERHOEFF_PERMUTATION[(index + offset) % 8][*value as usize];
+
+            VERHOEFF_MULTIPLICATION[checksum as usize][permuted as usize]
+        });
+
+    if compute { VERHOEFF_INVERSE[checksum as usize] }
+    else { checksum }
+}
+
+/// Calculate the interim digit of the Damm algorithm.
+fn damm(values: &[u8]) -> u8 {
+    values
+        .iter()
+        .fold(0, |interim, value| DAMM_QUASIGROUP[interim as usize][*value as usize])
+}
+
+/// Calculate the remainder of a decimal number of any length when divided by 97.
+fn mod97(values: &[u8]) -> u32 {
+    values
+        .iter()
+        .fold(0, |remainder, value| (remainder * 10 + *value as u32) % 97)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_luhn_check_digit() {
+        assert_eq!(Checksum::Luhn.compute(b"7992739871"), b"3");
+    }
+
+    #[test]
+    fn validates_luhn() {
+        assert!(Checksum::Luhn.validate(b"4111111111111111"));
+        assert!(!Checksum::Luhn.validate(b"4111111111111112"));
+    }
+
+    #[test]
+    fn computes_verhoeff_check_digit() {
+        assert_eq!(Checksum::Verhoeff.compute(b"236"), b"3");
+    }
+
+    #[test]
+    fn validates_verhoeff() {
+        assert!(Checksum::Verhoeff.validate(b"2363"));
+        assert!(!Checksum::Verhoeff.validate(b"2633"));
+    }
+
+    #[test]
+    fn computes_damm_check_digit() {
+        assert_eq!(Checksum::Damm.compute(b"572"), b"4");
+    }
+
+    #[test]
+    fn validates_damm() {
+        assert!(Checksum::Damm.validate(b"5724"));
+        assert!(!Checksum::Damm.validate(b"5274"));
+    }
+
+    #[test]
+    fn validates_mod97() {
+        // GB82 WEST 1234 5698 7654 32, rearranged and converted to digits.
+        assert!(Checksum::Mod97.validate(b"3214282912345698765432161182"));
+        assert!(!Checksum::Mod97.validate(b"3214282912345698765432161183"));
+    }
+
+    #[test]
+    fn computed_check_digits_are_valid() {
+        for checksum in [Checksum::Luhn, Checksum::Verhoeff, Checksum::Damm, Checksum::Mod97] {
+            let mut digits = b"0123456789".to_vec();
+
+            digits.extend(checksum.compute(&digits));
+
+            assert!(checksum.validate(&digits));
+        }
+    }
+
+    #[test]
+    fn rejects_non_digits() {
+        assert!(!Checksum::Luhn.validate(b"4111-1111"));
+        assert!(!Checksum::Luhn.validate(b"0"));
+    }
+}
@@ -1,10 +1,21 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{Parser, Subcommand, Args, ArgAction};
+use bytesize::ByteSize;
+use clap::{value_parser, Parser, Subcommand, Args, ArgAction};
 use log::LevelFilter;
+use num_bigint::BigInt;
 
-type BoxedError<'a> = Box<dyn std::error::Error + Send + Sync + 'a>;
-type UnitResult<'a> = Result<(), BoxedError<'a>>;
+use crate::checksum::Checksum;
+use crate::encoding::Encoding;
+use crate::error::{Error, Result};
+use crate::generators::{Base64Alphabet, Cidr, DiceNotation, KeyFormat, KeyType, MacStyle, OtpAlgorithm, TokenChecksum, UuidVersion, NANOID_ALPHABET};
+use crate::hashing::HashAlgorithm;
+use crate::kdf::KdfAlgorithm;
+use crate::output::TrailingNewline;
+use crate::record::RecordFormat;
+use crate::random::RngKind;
+use crate::shamir::ShareEncoding;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, arg_required_else_help = true, propagate_version = true)]
@@ -33,6 +44,38 @@ pub struct Verbosity {
 pub enum Commands {
     /// Generate a new secret key or username
     Generate {
+        #[arg(short = 'o', long = "output", help = "A path on a filesystem where secrets should be written with owner-only permissions, or leave empty to write to STDOUT", global = true)]
+        output: Option<PathBuf>,
+
+        #[arg(short = 'f', long = "force", help = "Replace output files, such as QR codes, if they already exist", global = true)]
+        force: bool,
+
+        #[arg(long = "rng", help = "The random number generator to draw secrets from [default: os, or chacha20 with --insecure-seed]", global = true)]
+        rng: Option<RngKind>,
+
+        // The fully-qualified path stops clap from treating the seed as a list of separate bytes.
+        #[arg(long = "insecure-seed", value_name = "HEX", help = "INSECURE: seed the generator with these hex-encoded bytes so that the output is reproducible, which is only suitable for test fixtures", value_parser = parse_hex, global = true)]
+        insecure_seed: Option<::std::vec::Vec<u8>>,
+
+        #[arg(long = "mix-entropy", value_name = "PATH", help = "Mix extra entropy from a file or device, or from lines typed on STDIN if the path is -, into the seed alongside the operating system's randomness", conflicts_with = "insecure_seed", global = true)]
+        mix_entropy: Option<PathBuf>,
+
+        // The fully-qualified path stops clap from treating the delimiter as a list of separate bytes.
+        #[arg(long = "output-delimiter", value_name = "TEXT", help = "The text written between secrets, which can include the escapes \\n, \\t, \\r, \\0 and \\\\ [default: \\n]", value_parser = parse_delimiter, allow_hyphen_values = true, global = true)]
+        output_delimiter: Option<::std::vec::Vec<u8>>,
+
+        #[arg(short = '0', long = "null", help = "Separate secrets with NUL bytes, for xargs -0", conflicts_with = "output_delimiter", global = true)]
+        null: bool,
+
+        #[arg(long = "trailing-newline", value_name = "WHEN", help = "Whether to write the delimiter after the last secret too, where auto only does so on a terminal", default_value = "auto", global = true)]
+        trailing_newline: TrailingNewline,
+
+        #[arg(long = "show-entropy", help = "Print how many bits of entropy each secret has on STDERR", global = true)]
+        show_entropy: bool,
+
+        #[arg(long = "format", value_name = "FORMAT", help = "Write each secret as a structured record, along with the type, parameters, alphabet size and entropy of the generator", conflicts_with_all = ["output_delimiter", "null", "trailing_newline"], global = true)]
+        output_format: Option<RecordFormat>,
+
         /// The sub-command to execute
         #[command(subcommand)]
         command: GenerateCommands
@@ -42,6 +85,104 @@ pub enum Commands {
         /// A path to a file on a filesystem, or leave empty to read from STDIN
         input: Option<PathBuf>
     },
+    /// Check whether sequences of digits end with valid check digits
+    Validate {
+        #[arg(short = 'c', long = "checksum", help = "The algorithm used to calculate the check digits")]
+        checksum: Checksum,
+
+        /// The sequences of digits to validate, or leave empty to read them line by line from STDIN
+        input: Vec<String>
+    },
+    /// Work with prefixed, checksummed API tokens
+    Token {
+        /// The sub-command to execute
+        #[command(subcommand)]
+        command: TokenCommands
+    },
+    /// Split a secret into shares, any threshold of which can reconstruct it
+    Split {
+        #[arg(short = 't', long = "threshold", help = "The number of shares that are needed to reconstruct the secret", value_parser = value_parser!(u8).range(2..))]
+        threshold: u8,
+
+        #[arg(short = 's', long = "shares", help = "The number of shares to create", value_parser = value_parser!(u8).range(2..))]
+        shares: u8,
+
+        #[arg(short = 'e', long = "encoding", help = "How each share is written", default_value = "hex")]
+        encoding: ShareEncoding,
+
+        #[arg(help = "A path to a file on a filesystem, or leave empty to read from STDIN")]
+        input: Option<PathBuf>,
+
+        /// Generate a new secret to split, rather than reading one
+        #[command(subcommand)]
+        command: Option<GenerateCommands>
+    },
+    /// Reconstruct a secret from enough of its shares
+    Combine {
+        #[arg(short = 'e', long = "encoding", help = "How each share is written", default_value = "hex")]
+        encoding: ShareEncoding,
+
+        #[arg(short = 'o', long = "output", help = "A path on a filesystem where the secret should be written with owner-only permissions, or leave empty to write to STDOUT")]
+        output: Option<PathBuf>,
+
+        #[arg(short = 'f', long = "force", help = "Replace the output file if it already exists")]
+        force: bool,
+
+        /// The shares to combine, or leave empty to read them line by line from STDIN
+        input: Vec<String>
+    },
+    /// Derive a key from a passphrase read on STDIN
+    Kdf {
+        #[arg(short = 'a', long = "algo", help = "The key derivation function", default_value = "argon2id")]
+        algorithm: KdfAlgorithm,
+
+        // The fully-qualified path stops clap from treating the salt as a list of separate bytes.
+        #[arg(short = 's', long = "salt", value_name = "HEX", help = "The hex-encoded salt, or leave empty to generate a new one and write it to STDERR", value_parser = parse_hex)]
+        salt: Option<::std::vec::Vec<u8>>,
+
+        #[arg(long = "info", help = "Context information that binds an HKDF key to its purpose")]
+        info: Option<String>,
+
+        #[arg(long = "cost", help = "The number of iterations, or the base-2 logarithm of N for scrypt")]
+        cost: Option<u32>,
+
+        #[arg(long = "memory", help = "The memory cost of Argon2id in KiB, or the most memory that calibration may choose")]
+        memory: Option<u32>,
+
+        #[arg(long = "parallelism", help = "The number of lanes of Argon2id or scrypt")]
+        parallelism: Option<u32>,
+
+        #[arg(long = "calibrate", value_name = "DURATION", help = "Print the parameters that take about this long on this machine, such as 500ms, instead of deriving a key", value_parser = parse_duration, conflicts_with_all = ["salt", "info", "cost", "output"])]
+        calibrate: Option<Duration>,
+
+        #[arg(short = 'e', long = "encoding", help = "How the key is written", default_value = "hex")]
+        encoding: Encoding,
+
+        #[arg(short = 'o', long = "output", help = "A path on a filesystem where the key should be written with owner-only permissions, or leave empty to write to STDOUT")]
+        output: Option<PathBuf>,
+
+        #[arg(short = 'f', long = "force", help = "Replace the output file if it already exists")]
+        force: bool,
+
+        /// The number of bytes to derive
+        #[arg(default_value_t = 32)]
+        length: usize
+    },
+    /// Print the lines of a file in a random order
+    Shuffle {
+        #[arg(long = "rng", help = "The random number generator to shuffle with [default: os, or chacha20 with --insecure-seed]")]
+        rng: Option<RngKind>,
+
+        // The fully-qualified path stops clap from treating the seed as a list of separate bytes.
+        #[arg(long = "insecure-seed", value_name = "HEX", help = "INSECURE: seed the generator with these hex-encoded bytes so that the order is reproducible, which is only suitable for test fixtures", value_parser = parse_hex)]
+        insecure_seed: Option<::std::vec::Vec<u8>>,
+
+        #[arg(short = 'n', long = "count", help = "Print at most this many lines")]
+        count: Option<usize>,
+
+        #[arg(help = "A path to a file on a filesystem, or leave empty to read from STDIN")]
+        input: Option<PathBuf>
+    },
     /// Create a visualization of an arbitary piece of data.
     Visualize {
         #[arg(help = "A path to a file on a filesystem, or leave empty to read from STDIN")]
@@ -56,25 +197,117 @@ pub enum Commands {
 pub enum GenerateCommands {
     /// Generate random bytes
     Bytes {
-        /// The number of bytes to generate
-        length: usize
+        #[arg(short = 'P', long = "progress", help = "Show the progress of long outputs on STDERR")]
+        progress: bool,
+
+        /// The number of bytes to generate, optionally followed by a unit such as KiB, MB or GiB
+        length: ByteSize
     },
     /// Generate random bytes and encode them as a hexadecimal string
     Hex {
         #[arg(short = 'u', long = "uppercase", help = "Print hexadecimal digits in uppercase")]
         uppercase: bool,
 
+        #[arg(long = "bits", help = "Generate the shortest string with at least this many bits of entropy", value_parser = value_parser!(u32).range(1..))]
+        bits: Option<u32>,
+
         /// The number of bytes to generate
-        length: usize
+        #[arg(required_unless_present = "bits", conflicts_with = "bits")]
+        length: Option<usize>
     },
     /// Generate random bytes and encode them as a Base64 string
     Base64 {
-        #[arg(short = 'u', long = "url-safe", help = "Use a URL-safe alphabet")]
+        #[arg(short = 'u', long = "url-safe", help = "Use a URL-safe alphabet", conflicts_with = "alphabet")]
         url_safe: bool,
 
+        #[arg(short = 'a', long = "alphabet", help = "The alphabet to encode bytes with", default_value = "standard")]
+        alphabet: Base64Alphabet,
+
+        #[arg(short = 'n', long = "no-pad", alias = "no-padding", help = "Don't pad the output to a multiple of four characters", action = ArgAction::SetFalse)]
+        padding: bool,
+
+        #[arg(short = 'w', long = "wrap", help = "Break the output into lines of this many characters, such as 64 or 76", value_parser = value_parser!(u64).range(1..))]
+        wrap: Option<u64>,
+
+        #[arg(long = "bits", help = "Generate the shortest string with at least this many bits of entropy", value_parser = value_parser!(u32).range(1..))]
+        bits: Option<u32>,
+
+        /// The number of bytes to generate
+        #[arg(required_unless_present = "bits", conflicts_with = "bits")]
+        length: Option<usize>
+    },
+    /// Generate random bytes and encode them as an RFC 4648 Base32 string
+    Base32 {
+        #[arg(short = 'l', long = "lowercase", help = "Print letters in lowercase")]
+        lowercase: bool,
+
+        #[arg(short = 'n', long = "no-pad", alias = "no-padding", help = "Don't pad the output to a multiple of eight characters", action = ArgAction::SetFalse)]
+        padding: bool,
+
+        /// The number of bytes to generate
+        length: usize
+    },
+    /// Generate random bytes and encode them as a Crockford Base32 string
+    Crockford {
+        #[arg(short = 'l', long = "lowercase", help = "Print letters in lowercase")]
+        lowercase: bool,
+
+        /// The number of bytes to generate
+        length: usize
+    },
+    /// Generate random bytes and encode them as a Base58 string
+    Base58 {
+        /// The number of bytes to generate
+        length: usize
+    },
+    /// Generate random bytes and encode them as a Z85 string
+    Z85 {
+        /// The number of bytes to generate, which must be a multiple of four
+        length: usize
+    },
+    /// Generate random bytes and encode them as an Ascii85 string
+    Base85 {
         /// The number of bytes to generate
         length: usize
     },
+    /// Generate an asymmetric key pair, printing the private key and writing the public key to STDERR
+    Keypair {
+        #[arg(short = 't', long = "type", help = "The algorithm to generate a key pair for", default_value = "ed25519")]
+        kind: KeyType,
+
+        #[arg(long = "key-format", help = "The format to write the key pair in", default_value = "openssh")]
+        key_format: KeyFormat
+    },
+    /// Generate a secret for time-based one-time passwords, along with an otpauth URI
+    Totp {
+        #[command(flatten)]
+        options: OtpOptions,
+
+        #[arg(long = "period", help = "The number of seconds that each password is valid for", default_value_t = 30, value_parser = value_parser!(u64).range(1..))]
+        period: u64
+    },
+    /// Generate a secret for counter-based one-time passwords, along with an otpauth URI
+    Hotp {
+        #[command(flatten)]
+        options: OtpOptions,
+
+        #[arg(long = "counter", help = "The initial value of the counter", default_value_t = 0)]
+        counter: u64
+    },
+    /// Generate an API token with a prefix and a checksum that secret scanners can verify offline
+    Token {
+        #[arg(short = 'p', long = "prefix", help = "A prefix that identifies what the token is for, which is separated from the rest by an underscore", default_value = "")]
+        prefix: String,
+
+        #[arg(short = 'b', long = "bytes", help = "The number of random bytes in the body of the token", default_value_t = 30)]
+        length: usize,
+
+        #[arg(short = 'c', long = "checksum", help = "The algorithm used to calculate the checksum at the end of the token", default_value = "crc32")]
+        checksum: TokenChecksum,
+
+        /// How many tokens to generate
+        count: Option<usize>
+    },
     /// Generate a random password with a configurable character set
     Password {
         #[arg(short = 'D', long = "no-digits", help = "Don't include any digits", action = ArgAction::SetFalse)]
@@ -83,8 +316,14 @@ pub enum GenerateCommands {
         #[arg(short = 'S', long = "no-symbols", help = "Don't include any symbols", action = ArgAction::SetFalse)]
         symbols: bool,
 
-        /// The number of characters to generate
-        length: usize,
+        #[command(flatten)]
+        hashing: HashOptions,
+
+        #[arg(long = "bits", help = "Generate the shortest password with at least this many bits of entropy instead of a length", value_parser = value_parser!(u32).range(1..))]
+        bits: Option<u32>,
+
+        #[arg(short = 'l', long = "length", help = "The number of characters to generate", required_unless_present = "bits", conflicts_with = "bits")]
+        length: Option<usize>,
 
         /// How many passwords to generate
         count: Option<usize>
@@ -100,8 +339,14 @@ pub enum GenerateCommands {
         #[arg(short = 's', long = "separator", help = "A string used to separate words in the passphrase", default_value = " ")]
         separator: String,
 
-        /// The number of words to generate
-        length: usize,
+        #[command(flatten)]
+        hashing: HashOptions,
+
+        #[arg(long = "bits", help = "Generate the shortest passphrase with at least this many bits of entropy instead of a length", value_parser = value_parser!(u32).range(1..))]
+        bits: Option<u32>,
+
+        #[arg(short = 'l', long = "length", help = "The number of words to generate", required_unless_present = "bits", conflicts_with = "bits")]
+        length: Option<usize>,
 
         /// How many passphrases to generate
         count: Option<usize>
@@ -114,41 +359,222 @@ pub enum GenerateCommands {
         #[command(subcommand)]
         command: UsernameCommands
     },
+    /// Generate random network identifiers for lab automation
+    Network {
+        #[command(subcommand)]
+        command: NetworkCommands
+    },
     /// Generate a random sequence of digits
     Digits {
-        /// The number of digits to generate
-        length: usize,
+        #[arg(short = 'c', long = "checksum", help = "Append check digits calculated with this algorithm")]
+        checksum: Option<Checksum>,
+
+        #[arg(short = 'p', long = "prefix", help = "A sequence of digits that every generated sequence should begin with", default_value = "")]
+        prefix: String,
+
+        #[arg(short = 's', long = "secure-pin", help = "Reject common PINs, repeated patterns, runs and dates", conflicts_with_all = ["checksum", "prefix"])]
+        secure_pin: bool,
+
+        #[arg(short = 't', long = "top", help = "How many of the most common PINs to reject in secure PIN mode", default_value_t = 100, requires = "secure_pin")]
+        top: usize,
+
+        #[arg(long = "bits", help = "Generate the shortest sequence of digits with at least this many bits of entropy instead of a length", value_parser = value_parser!(u32).range(1..))]
+        bits: Option<u32>,
+
+        #[arg(short = 'l', long = "length", help = "The number of digits to generate, including the prefix and any check digits", required_unless_present = "bits", conflicts_with = "bits")]
+        length: Option<usize>,
 
         /// How many sequences of digits to generate
         count: Option<usize>
     },
     /// Generate a random number
+    #[command(allow_negative_numbers = true)]
     Number {
+        #[arg(short = 'p', long = "precision", help = "The number of decimal places to generate", default_value_t = 0)]
+        precision: u32,
+
+        #[arg(short = 'r', long = "radix", help = "The base in which numbers are printed", default_value_t = 10, value_parser = value_parser!(u32).range(2..=36))]
+        radix: u32,
+
+        #[arg(short = 's', long = "step", help = "Only generate numbers that are a multiple of this step above the minimum")]
+        step: Option<String>,
+
+        #[arg(short = 'U', long = "unique", help = "Never generate the same number more than once")]
+        unique: bool,
+
         /// The smallest number that can be generated
-        minimum: usize,
+        minimum: String,
 
         /// The largest number that can be generated
-        maximum: usize,
+        maximum: String,
 
         /// How many numbers to generate
         count: Option<usize>
+    },
+    /// Generate a random UUID
+    #[command(disable_version_flag = true)]
+    Uuid {
+        #[arg(short = 'V', long = "version", help = "The version of UUID to generate", default_value = "4")]
+        version: UuidVersion,
+
+        #[arg(short = 'm', long = "monotonic", help = "Make every UUID sort after the one before it, even within the same millisecond")]
+        monotonic: bool,
+
+        /// How many UUIDs to generate
+        count: Option<usize>
+    },
+    /// Generate a Universally Unique Lexicographically Sortable Identifier
+    Ulid {
+        #[arg(short = 'm', long = "monotonic", help = "Make every ULID sort after the one before it, even within the same millisecond")]
+        monotonic: bool,
+
+        /// How many ULIDs to generate
+        count: Option<usize>
+    },
+    /// Generate a compact, URL-safe random identifier
+    Nanoid {
+        #[arg(short = 'a', long = "alphabet", help = "The characters that the identifier is composed of", default_value = NANOID_ALPHABET)]
+        alphabet: String,
+
+        #[arg(short = 's', long = "size", help = "The number of characters to generate", default_value_t = 21)]
+        size: usize,
+
+        /// How many identifiers to generate
+        count: Option<usize>
+    },
+    /// Generate a K-Sortable Unique Identifier
+    Ksuid {
+        /// How many KSUIDs to generate
+        count: Option<usize>
+    },
+    /// Roll dice described in standard dice notation, such as 3d6+2
+    Dice {
+        /// The dice to roll, such as 3d6+2, d20 or 2d%
+        notation: DiceNotation,
+
+        /// How many times to roll the dice
+        count: Option<usize>
+    },
+    /// Flip a fair coin
+    Coin {
+        /// How many times to flip the coin
+        count: Option<usize>
+    },
+    /// Draw playing cards from one or more shuffled decks
+    Cards {
+        #[arg(long = "decks", help = "The number of standard 52-card decks to shuffle together", default_value_t = 1)]
+        decks: usize,
+
+        #[arg(short = 'n', long = "draw", help = "The number of cards to draw, or leave empty to draw every card")]
+        draw: Option<usize>
+    },
+    /// Generate a random permutation of consecutive integers
+    #[command(allow_negative_numbers = true)]
+    Permutation {
+        #[arg(short = 's', long = "start", help = "The first integer in the sequence", default_value = "1")]
+        start: BigInt,
+
+        /// The number of integers to permute
+        length: usize
+    }
+}
+
+#[derive(Subcommand)]
+pub enum NetworkCommands {
+    /// Generate a random locally-administered unicast MAC address
+    Mac {
+        #[arg(short = 's', long = "style", help = "How the octets of the address are grouped", default_value = "colon")]
+        style: MacStyle,
+
+        #[arg(short = 'u', long = "uppercase", help = "Print hexadecimal digits in uppercase")]
+        uppercase: bool,
+
+        /// How many MAC addresses to generate
+        count: Option<usize>
+    },
+    /// Generate a random RFC 4193 unique local IPv6 /48 prefix
+    Ula {
+        /// How many prefixes to generate
+        count: Option<usize>
+    },
+    /// Generate a random IPv4 or IPv6 address inside a block of addresses
+    Ip {
+        #[arg(short = 'c', long = "cidr", help = "The block of addresses in CIDR notation, such as 10.0.0.0/8 or fd00::/64")]
+        cidr: Cidr,
+
+        /// How many addresses to generate
+        count: Option<usize>
     }
 }
 
+#[derive(Subcommand)]
+pub enum TokenCommands {
+    /// Check whether tokens end with a valid checksum
+    Verify {
+        #[arg(short = 'p', long = "prefix", help = "Also require every token to begin with this prefix")]
+        prefix: Option<String>,
+
+        #[arg(short = 'c', long = "checksum", help = "The algorithm used to calculate the checksum at the end of the token", default_value = "crc32")]
+        checksum: TokenChecksum,
+
+        /// The tokens to verify, or leave empty to read them line by line from STDIN
+        input: Vec<String>
+    }
+}
+
+#[derive(Args)]
+pub struct OtpOptions {
+    #[arg(short = 'i', long = "issuer", help = "The name of the service that the secret is for")]
+    pub issuer: Option<String>,
+
+    #[arg(short = 'a', long = "account", help = "The name of the account that the secret is for, such as an email address")]
+    pub account: String,
+
+    #[arg(long = "algorithm", help = "The HMAC algorithm that passwords are computed with, which also determines the length of the secret", default_value = "sha1")]
+    pub algorithm: OtpAlgorithm,
+
+    #[arg(long = "digits", help = "The number of digits in each password", default_value_t = 6, value_parser = value_parser!(u32).range(6..=8))]
+    pub digits: u32,
+
+    #[arg(long = "qr", help = "A path on a filesystem where the otpauth URI should be saved as a QR code in PNG format")]
+    pub qr_code: Option<PathBuf>
+}
+
+#[derive(Args)]
+pub struct HashOptions {
+    #[arg(long = "hash", help = "Write a hash of each secret after it, separated by a tab")]
+    pub algorithm: Option<HashAlgorithm>,
+
+    #[arg(long = "cost", help = "The time cost of the hash, which is the number of iterations, its base-2 logarithm for bcrypt and scrypt, or from 1 to 11 for yescrypt", requires = "algorithm")]
+    pub cost: Option<u32>,
+
+    #[arg(long = "memory", help = "The memory cost of an Argon2id hash in KiB", requires = "algorithm")]
+    pub memory: Option<u32>,
+
+    #[arg(long = "parallelism", help = "The number of lanes of an Argon2id or scrypt hash", requires = "algorithm")]
+    pub parallelism: Option<u32>
+}
+
 #[derive(Subcommand)]
 pub enum UsernameCommands {
     /// Generate a simple pronounceable username that alternates between vowels and consonants
     Simple {
-        /// The number of characters to generate
-        length: usize,
+        #[arg(long = "bits", help = "Generate the shortest username with at least this many bits of entropy instead of a length", value_parser = value_parser!(u32).range(1..))]
+        bits: Option<u32>,
+
+        #[arg(short = 'l', long = "length", help = "The number of characters to generate", required_unless_present = "bits", conflicts_with = "bits")]
+        length: Option<usize>,
 
         /// How many simple usernames to generate
         count: Option<usize>
     },
     /// Generate a complex pronounceable username that is constructed from syllables
     Complex {
-        /// The number of syllables to generate
-        length: usize,
+        #[arg(long = "bits", help = "Generate the shortest username with at least this many bits of entropy instead of a length", value_parser = value_parser!(u32).range(1..))]
+        bits: Option<u32>,
+
+        #[arg(short = 'l', long = "length", help = "The number of syllables to generate", required_unless_present = "bits", conflicts_with = "bits")]
+        length: Option<usize>,
 
         /// How many syllabic usernames to generate
         count: Option<usize>
@@ -164,11 +590,66 @@ impl Verbosity {
     }
 }
 
+fn parse_hex(value: &str) -> std::result::Result<Vec<u8>, String> {
+    let bytes = hex::decode(value).map_err(|error| format!("{value} is not a valid hex string: {error}"))?;
+
+    if bytes.is_empty() { return Err("At least one byte must be given".into()); }
+
+    Ok(bytes)
+}
+
+/// Parse a delimiter, replacing escapes so that characters such as tabs can be typed on the command line.
+fn parse_delimiter(value: &str) -> std::result::Result<Vec<u8>, String> {
+    let mut delimiter = String::with_capacity(value.len());
+    let mut characters = value.chars();
+
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            delimiter.push(character);
+
+            continue;
+        }
+
+        let escaped = match characters.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some(other) => return Err(format!("\\{other} is not a supported escape")),
+            None => return Err("The delimiter cannot end with a lone backslash".into())
+        };
+
+        delimiter.push(escaped);
+    }
+
+    Ok(delimiter.into_bytes())
+}
+
+fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let invalid = || format!("{value} is not a valid duration, such as 500ms or 2s");
+    let (number, unit) = value
+        .find(|character: char| !character.is_ascii_digit())
+        .map(|index| value.split_at(index))
+        .ok_or_else(invalid)?;
+    let number = number.parse::<u64>().map_err(|_| invalid())?;
+
+    let duration = match unit {
+        "ms" => Duration::from_millis(number),
+        "s" => Duration::from_secs(number),
+        _ => return Err(invalid())
+    };
+
+    if duration.is_zero() { return Err(invalid()); }
+
+    Ok(duration)
+}
+
 pub fn parse() -> Arguments {
     Arguments::parse()
 }
 
-pub fn setup_logging<'a>(verbosity: &Verbosity) -> UnitResult<'a> {
+pub fn setup_logging(verbosity: &Verbosity) -> Result<()> {
     let filter = verbosity.to_filter();
 
     env_logger::builder()
@@ -178,7 +659,8 @@ pub fn setup_logging<'a>(verbosity: &Verbosity) -> UnitResult<'a> {
         .format_module_path(false)
         .format_timestamp_secs()
         .parse_default_env()
-        .try_init()?;
+        .try_init()
+        .map_err(|error| Error::Internal(error.to_string()))?;
 
     Ok(())
 }
@@ -0,0 +1,332 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::ValueEnum;
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// The RFC 4648 Base32 alphabet.
+pub const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Douglas Crockford's Base32 alphabet, which excludes the easily-confused letters I, L, O and U.
+pub const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The Bitcoin Base58 alphabet, which excludes the easily-confused glyphs 0, O, I and l.
+pub const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// The Base62 alphabet, in the order used by KSUIDs and GitHub-style tokens.
+pub const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The ZeroMQ Base85 alphabet, which is safe to embed in source code and XML.
+pub const Z85: &[u8; 85] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// The encodings that derived keys can be written in.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Encoding {
+    /// The bytes themselves, without any encoding
+    Raw,
+    /// Lowercase hexadecimal
+    Hex,
+    /// Padded Base64 with the standard alphabet from RFC 4648
+    Base64,
+    /// Padded RFC 4648 Base32
+    Base32,
+    /// Crockford Base32
+    Crockford,
+    /// Bitcoin Base58
+    Base58,
+    /// ZeroMQ Base85, which can only encode a multiple of four bytes
+    Z85,
+    /// Ascii85
+    Base85
+}
+
+impl Encoding {
+    /// Encode bytes as text, or leave them untouched if they should be written raw.
+    pub fn encode(self, bytes: &[u8]) -> Result<Secret> {
+        let secret = match self {
+            Encoding::Raw => Secret::from(bytes),
+            // Both of these allocate exactly the length of the output, which the secret then takes ownership of.
+            Encoding::Hex => Secret::from(hex::encode(bytes)),
+            Encoding::Base64 => Secret::from(STANDARD.encode(bytes)),
+            Encoding::Base32 => encode_base32(bytes, BASE32, true),
+            Encoding::Crockford => encode_base32(bytes, CROCKFORD, false),
+            Encoding::Base58 => encode_radix(bytes, BASE58),
+            Encoding::Z85 => encode_z85(bytes)
+                .ok_or_else(|| Error::InvalidArgument(format!("Z85 can only encode a multiple of four bytes, but there are {} bytes", bytes.len())))?,
+            Encoding::Base85 => encode_ascii85(bytes)
+        };
+
+        Ok(secret)
+    }
+}
+
+/// Encode bytes as Base32 with the given alphabet, optionally padding the output to a multiple of eight characters.
+pub fn encode_base32(bytes: &[u8], alphabet: &[u8; 32], padding: bool) -> Secret {
+    let mut output = Secret::with_capacity(bytes.len().div_ceil(5) * 8);
+
+    for chunk in bytes.chunks(5) {
+        let mut block = [0_u8; 5];
+
+        block[..chunk.len()].copy_from_slice(chunk);
+
+        let value = block.iter().fold(0_u64, |value, byte| (value << 8) | *byte as u64);
+        let characters = (chunk.len() * 8).div_ceil(5);
+
+        for index in 0..characters {
+            let position = (value >> (35 - index * 5)) & 0x1F;
+
+            output.push(alphabet[position as usize]);
+        }
+
+        if padding {
+            for _ in characters..8 { output.push(b'='); }
+        }
+    }
+
+    output
+}
+
+/// Decode unpadded Base32 with the given alphabet, returning `None` if it contains any other character or has an impossible length.
+pub fn decode_base32(string: &str, alphabet: &[u8; 32]) -> Option<Vec<u8>> {
+    if matches!(string.len() % 8, 1 | 3 | 6) { return None; }
+
+    let mut output = Vec::<u8>::with_capacity(string.len() * 5 / 8);
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+
+    for character in string.bytes() {
+        let value = alphabet.iter().position(|symbol| *symbol == character)? as u32;
+
+        buffer = (buffer << 5) | value;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(output)
+}
+
+/// Convert bytes to the big-endian digits of the same number in any radix below 2^24, ignoring leading zero bytes.
+///
+/// The digits are wiped when they are dropped, since they are the secret in another form.
+pub fn to_radix(bytes: &[u8], radix: u32) -> Zeroizing<Vec<u32>> {
+    // The digits of the number, least significant first, which never need more room than one for each bit, so the buffer never grows.
+    let mut digits = Zeroizing::new(Vec::<u32>::with_capacity(bytes.len() * 8));
+
+    for byte in bytes.iter().skip_while(|byte| **byte == 0) {
+        let mut carry = *byte as u32;
+
+        for digit in digits.iter_mut() {
+            carry += *digit << 8;
+            *digit = carry % radix;
+            carry /= radix;
+        }
+
+        while carry > 0 {
+            digits.push(carry % radix);
+            carry /= radix;
+        }
+    }
+
+    digits.reverse();
+
+    digits
+}
+
+/// Convert big-endian digits in any radix below 2^24 back to the bytes of the same number, without any leading zero bytes.
+///
+/// Returns `None` if any digit is too large for the radix.
+pub fn from_radix(digits: &[u32], radix: u32) -> Option<Vec<u8>> {
+    // The bytes of the number, least significant first.
+    let mut bytes = Vec::<u8>::with_capacity(digits.len() * 4);
+
+    for digit in digits {
+        if *digit >= radix { return None; }
+
+        let mut carry = *digit;
+
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * radix;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    bytes.reverse();
+
+    Some(bytes)
+}
+
+/// Encode bytes as a big-endian number in the base given by the length of the alphabet.
+///
+/// Every leading zero byte is preserved as a leading zero digit, as in Base58.
+pub fn encode_radix(bytes: &[u8], alphabet: &[u8]) -> Secret {
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    let digits = to_radix(bytes, alphabet.len() as u32);
+    let leading = std::iter::repeat_n(alphabet[0], zeros);
+    let rest = digits.iter().map(|digit| alphabet[*digit as usize]);
+
+    leading.chain(rest).collect()
+}
+
+/// Encode bytes as a number in the base given by the length of the alphabet, padded with leading zero digits to exactly `width` digits.
+///
+/// Unlike [`encode_radix`], leading zero bytes don't lengthen the output, so `width` only needs to fit the largest possible number.
+pub fn encode_fixed_radix(bytes: &[u8], alphabet: &[u8], width: usize) -> Secret {
+    let digits = to_radix(bytes, alphabet.len() as u32);
+
+    std::iter::repeat_n(alphabet[0], width.saturating_sub(digits.len()))
+        .chain(digits.iter().map(|digit| alphabet[*digit as usize]))
+        .collect()
+}
+
+/// Encode bytes as Z85, which requires the input to be a multiple of four bytes long.
+pub fn encode_z85(bytes: &[u8]) -> Option<Secret> {
+    if !bytes.len().is_multiple_of(4) { return None; }
+
+    let mut output = Secret::with_capacity(bytes.len() / 4 * 5);
+
+    for chunk in bytes.chunks(4) {
+        encode_base85_block(chunk, Z85, &mut output);
+    }
+
+    Some(output)
+}
+
+/// Encode bytes as Ascii85, abbreviating every block of four zero bytes as `z`.
+pub fn encode_ascii85(bytes: &[u8]) -> Secret {
+    let alphabet = (b'!'..=b'u').collect::<Vec<u8>>();
+    let mut output = Secret::with_capacity(bytes.len().div_ceil(4) * 5);
+
+    for chunk in bytes.chunks(4) {
+        if chunk == [0, 0, 0, 0] { output.push(b'z'); }
+        else { encode_base85_block(chunk, &alphabet, &mut output); }
+    }
+
+    output
+}
+
+/// Encode up to four bytes as one more Base85 digit than there are bytes, appending them to `output`.
+fn encode_base85_block(chunk: &[u8], alphabet: &[u8], output: &mut Secret) {
+    let mut block = [0_u8; 4];
+
+    block[..chunk.len()].copy_from_slice(chunk);
+
+    let mut value = u32::from_be_bytes(block);
+    let mut digits = [0_u8; 5];
+
+    for digit in digits.iter_mut().rev() {
+        *digit = alphabet[(value % 85) as usize];
+        value /= 85;
+    }
+
+    output.extend_from_slice(&digits[..chunk.len() + 1]);
+}
+
+/// Break ASCII text into lines of at most `width` characters, separated by line feeds.
+pub fn wrap_lines(text: &[u8], width: usize) -> Secret {
+    let mut output = Secret::with_capacity(text.len() + text.len() / width);
+
+    for (index, line) in text.chunks(width).enumerate() {
+        if index > 0 { output.push(b'\n'); }
+
+        output.extend_from_slice(line);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_base32_test_vectors() {
+        assert_eq!(encode_base32(b"", BASE32, true), "");
+        assert_eq!(encode_base32(b"f", BASE32, true), "MY======");
+        assert_eq!(encode_base32(b"fo", BASE32, true), "MZXQ====");
+        assert_eq!(encode_base32(b"foo", BASE32, true), "MZXW6===");
+        assert_eq!(encode_base32(b"foob", BASE32, true), "MZXW6YQ=");
+        assert_eq!(encode_base32(b"fooba", BASE32, true), "MZXW6YTB");
+        assert_eq!(encode_base32(b"foobar", BASE32, true), "MZXW6YTBOI======");
+        assert_eq!(encode_base32(b"foobar", BASE32, false), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn decodes_base32_test_vectors() {
+        assert_eq!(decode_base32("", BASE32).unwrap(), b"");
+        assert_eq!(decode_base32("MY", BASE32).unwrap(), b"f");
+        assert_eq!(decode_base32("MZXW6YQ", BASE32).unwrap(), b"foob");
+        assert_eq!(decode_base32("MZXW6YTBOI", BASE32).unwrap(), b"foobar");
+        assert!(decode_base32("MZXW6YTBO", BASE32).is_none());
+        assert!(decode_base32("mzxw6ytboi", BASE32).is_none());
+    }
+
+    #[test]
+    fn encodes_crockford_base32() {
+        assert_eq!(encode_base32(b"foobar", CROCKFORD, false), "CSQPYRK1E8");
+    }
+
+    #[test]
+    fn encodes_base58_test_vectors() {
+        assert_eq!(encode_radix(b"", BASE58), "");
+        assert_eq!(encode_radix(b"hello world", BASE58), "StV1DL6CwTryKyV");
+        assert_eq!(encode_radix(&[0, 0, 0x28, 0x7F, 0xB4, 0xCD], BASE58), "11233QC4");
+    }
+
+    #[test]
+    fn converts_bytes_to_and_from_radix() {
+        assert_eq!(*to_radix(&[0, 0x01, 0x00], 7776), [256]);
+        assert_eq!(*to_radix(&[0xFF, 0xFF, 0xFF, 0xFF], 7776), [71, 240, 2559]);
+        assert_eq!(from_radix(&[71, 240, 2559], 7776).unwrap(), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert!(from_radix(&[7776], 7776).is_none());
+    }
+
+    #[test]
+    fn encodes_fixed_width_base62() {
+        assert_eq!(encode_fixed_radix(&[0, 0, 0, 61], BASE62, 6), "00000z");
+        assert_eq!(encode_fixed_radix(&[0, 0, 0, 62], BASE62, 6), "000010");
+        assert_eq!(encode_fixed_radix(&[0xFF, 0xFF, 0xFF, 0xFF], BASE62, 6), "4gfFC3");
+    }
+
+    #[test]
+    fn encodes_z85_test_vector() {
+        let bytes = [0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B];
+
+        assert_eq!(encode_z85(&bytes).unwrap(), "HelloWorld");
+        assert!(encode_z85(&bytes[..3]).is_none());
+    }
+
+    #[test]
+    fn wraps_lines() {
+        assert_eq!(wrap_lines(b"abcdefg", 3), b"abc\ndef\ng");
+        assert_eq!(wrap_lines(b"abcdef", 3), b"abc\ndef");
+    }
+
+    #[test]
+    fn encodes_ascii85_test_vectors() {
+        assert_eq!(encode_ascii85(b"Man "), "9jqo^");
+        assert_eq!(encode_ascii85(b"sure."), "F*2M7/c");
+        assert_eq!(encode_ascii85(&[0, 0, 0, 0]), "z");
+    }
+
+    #[test]
+    fn encodes_with_chosen_encoding() {
+        assert_eq!(Encoding::Raw.encode(b"\x00\xFF").unwrap(), b"\x00\xFF");
+        assert_eq!(Encoding::Hex.encode(b"\x00\xFF").unwrap(), b"00ff");
+        assert_eq!(Encoding::Base64.encode(b"foob").unwrap(), b"Zm9vYg==");
+        assert_eq!(Encoding::Base32.encode(b"foob").unwrap(), b"MZXW6YQ=");
+        assert!(matches!(Encoding::Z85.encode(b"foo"), Err(Error::InvalidArgument(_))));
+    }
+}
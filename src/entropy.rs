@@ -0,0 +1,95 @@
+use num_bigint::BigInt;
+
+use crate::error::{Error, Result};
+
+/// The longest secret that `minimum_length` will consider, in symbols.
+const MAXIMUM_LENGTH: usize = 1 << 24;
+
+/// The base-2 logarithm of a number of possibilities that may be too large to fit in a float.
+pub fn log2(size: &BigInt) -> f64 {
+    let bits = size.bits();
+
+    if bits <= 64 { return (u64::try_from(size).unwrap_or(0) as f64).log2(); }
+
+    // Keep the 64 most significant bits, which is all that a float can represent anyway.
+    let shift = bits - 64;
+    let top = u64::try_from(size >> shift).unwrap_or(u64::MAX);
+
+    (top as f64).log2() + shift as f64
+}
+
+/// The number of bits of entropy in `length` symbols, each chosen uniformly from `alphabet` of them.
+pub fn uniform_entropy(alphabet: usize, length: usize) -> f64 {
+    length as f64 * (alphabet as f64).log2()
+}
+
+/// The average bits of entropy in each of `draws` items drawn without replacement from `size` of them, which is log2(size! / (size - draws)!) / draws.
+///
+/// Every draw has one fewer item left to choose from than the one before it, so only the first has the entropy of the whole set.
+pub fn drawn_entropy(size: &BigInt, draws: usize) -> f64 {
+    if draws == 0 { return 0.0; }
+
+    let total = (0..draws)
+        .map(|drawn| log2(&(size - drawn)))
+        .sum::<f64>();
+
+    total / draws as f64
+}
+
+/// Find the shortest length that gives a secret at least `bits` bits of entropy, where `entropy` calculates the bits for a length and never decreases as it grows.
+///
+/// The same function that describes a secret is used to size it, so that the length always agrees with the entropy that is reported.
+pub fn minimum_length(bits: u32, entropy: impl Fn(usize) -> f64) -> Result<usize> {
+    let target = bits as f64;
+    let infeasible = || Error::PolicyInfeasible(format!("No secret of up to {MAXIMUM_LENGTH} symbols has {bits} bits of entropy"));
+
+    // Double the length until it is long enough, then search for the shortest one between it and half of it.
+    let mut upper = 1;
+
+    while entropy(upper) < target {
+        upper *= 2;
+
+        if upper > MAXIMUM_LENGTH { return Err(infeasible()); }
+    }
+
+    let mut lower = upper / 2;
+
+    while upper - lower > 1 {
+        let middle = lower + (upper - lower) / 2;
+
+        if entropy(middle) >= target { upper = middle; } else { lower = middle; }
+    }
+
+    Ok(upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_logarithm_of_large_numbers() {
+        assert_eq!(log2(&BigInt::from(1024)), 10.0);
+        assert!((log2(&(BigInt::from(1) << 300)) - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn averages_entropy_of_draws_without_replacement() {
+        assert_eq!(drawn_entropy(&BigInt::from(4), 1), 2.0);
+        assert!((drawn_entropy(&BigInt::from(4), 4) - 24_f64.log2() / 4.0).abs() < 1e-9);
+        assert!((drawn_entropy(&BigInt::from(52), 52) * 52.0 - 225.581).abs() < 1e-3);
+        assert_eq!(drawn_entropy(&BigInt::from(4), 0), 0.0);
+    }
+
+    #[test]
+    fn finds_shortest_length_for_entropy() {
+        assert_eq!(minimum_length(128, |length| uniform_entropy(16, length)).unwrap(), 32);
+        assert_eq!(minimum_length(128, |length| uniform_entropy(68, length)).unwrap(), 22);
+        assert_eq!(minimum_length(1, |length| uniform_entropy(2, length)).unwrap(), 1);
+    }
+
+    #[test]
+    fn rejects_alphabet_without_entropy() {
+        assert!(matches!(minimum_length(8, |length| uniform_entropy(1, length)), Err(Error::PolicyInfeasible(_))));
+    }
+}
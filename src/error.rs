@@ -0,0 +1,114 @@
+use std::{fmt, io};
+
+use png::EncodingError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error that prevents CipherGen from completing a command.
+///
+/// Every kind of failure maps to a distinct exit code, so that scripts can tell a mistake in the arguments apart from a failure to read or write data.
+#[derive(Debug)]
+pub enum Error {
+    /// An argument or a piece of input is malformed.
+    InvalidArgument(String),
+    /// A range of numbers is empty.
+    InvalidRange(String),
+    /// A character set or wordlist has nothing to choose from.
+    EmptyAlphabet(String),
+    /// No output can satisfy every constraint that was requested.
+    PolicyInfeasible(String),
+    /// A piece of input failed verification, such as a sequence of digits with invalid check digits.
+    VerificationFailed(String),
+    /// Reading or writing data failed.
+    Io(io::Error),
+    /// The output was closed before everything could be written to it, which stops the command but is not a failure.
+    BrokenPipe,
+    /// An unexpected failure that does not belong to any other kind.
+    Internal(String)
+}
+
+impl Error {
+    /// The exit code that the process should terminate with when this error occurs.
+    ///
+    /// Exit code 2 is reserved for errors in the command-line syntax, which are reported before any command is run.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Internal(_) => 1,
+            Error::InvalidArgument(_) => 3,
+            Error::InvalidRange(_) => 4,
+            Error::EmptyAlphabet(_) => 5,
+            Error::PolicyInfeasible(_) => 6,
+            Error::VerificationFailed(_) => 7,
+            Error::Io(_) => 8,
+            // Like coreutils, a reader that stops early isn't treated as a failure, so pipelines under `set -o pipefail` still succeed.
+            Error::BrokenPipe => 0
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidArgument(message)
+            | Error::InvalidRange(message)
+            | Error::EmptyAlphabet(message)
+            | Error::PolicyInfeasible(message)
+            | Error::VerificationFailed(message)
+            | Error::Internal(message) => write!(formatter, "{message}"),
+            Error::Io(error) => write!(formatter, "{error}"),
+            Error::BrokenPipe => write!(formatter, "The output was closed before everything could be written to it")
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::BrokenPipe => Error::BrokenPipe,
+            _ => Error::Io(error)
+        }
+    }
+}
+
+impl From<EncodingError> for Error {
+    fn from(error: EncodingError) -> Self {
+        match error {
+            EncodingError::IoError(error) => error.into(),
+            error => Error::Internal(error.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_pipe_is_distinguished_from_other_io_errors() {
+        let broken = Error::from(io::Error::from(io::ErrorKind::BrokenPipe));
+        let missing = Error::from(io::Error::from(io::ErrorKind::NotFound));
+
+        assert!(matches!(broken, Error::BrokenPipe));
+        assert!(matches!(missing, Error::Io(_)));
+        assert_ne!(broken.exit_code(), missing.exit_code());
+        assert_eq!(broken.exit_code(), 0);
+    }
+
+    #[test]
+    fn user_errors_are_distinguished_from_io_errors() {
+        let range = Error::InvalidRange(String::new());
+        let io = Error::Io(io::Error::from(io::ErrorKind::NotFound));
+
+        assert_ne!(range.exit_code(), io.exit_code());
+        assert_ne!(range.exit_code(), 2);
+    }
+}
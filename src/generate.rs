@@ -1,67 +1,450 @@
-use std::sync::{atomic::{AtomicUsize, Ordering}, mpsc::Sender};
+use std::collections::HashSet;
+use std::io::{stderr, Write};
+use std::sync::mpsc::SyncSender;
 
+use log::info;
+use num_bigint::BigInt;
+use rayon::current_num_threads;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use crate::checksum::Checksum;
+use crate::entropy::{drawn_entropy, log2, uniform_entropy};
+use crate::error::{Error, Result};
 use crate::generators::*;
+use crate::hashing::HashSettings;
+use crate::output::SecureFile;
+use crate::pins::effective_entropy;
+use crate::progress::Progress;
+use crate::random::{Random, RngSource};
+use crate::record::{value_name, Metadata, Record};
+use crate::secret::Secret;
+use crate::visualize::render_qr_code;
 
-const LINE_FEED: u8 = b'\n';
+/// The number of messages that can wait to be written before generators are made to wait, which bounds the memory used by the pipeline.
+pub const CHANNEL_CAPACITY: usize = 64;
+
+/// The number of bytes that are generated and sent at a time when streaming raw bytes.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The number of records that each thread generates at a time, which bounds how far the generators can run ahead of the output.
+const BATCH_SIZE_PER_THREAD: usize = 16;
 
 pub enum UsernameKind {
     Simple,
     Complex
 }
 
-fn create_serial(sender: Sender<Vec<u8>>, closure: impl FnOnce() -> Vec<u8>) {
-    let buffer = closure();
+/// Send a message to the thread that writes the output, failing if it has stopped listening.
+fn send(sender: &SyncSender<Record>, record: Record) -> Result<()> {
+    sender.send(record).map_err(|_| Error::BrokenPipe)
+}
+
+fn create_serial(sender: SyncSender<Record>, source: &RngSource, metadata: Metadata, closure: impl FnOnce(&mut Random) -> Result<Secret>) -> Result<()> {
+    let secret = closure(&mut source.stream(0))?;
+
+    send(&sender, Record::new(secret, &metadata.shared()))
+}
+
+/// Generate secrets in order on a single thread, sending each one as soon as it is produced so that only the channel holds them.
+fn create_sequence<I: Iterator<Item = Result<Secret>>>(sender: SyncSender<Record>, source: &RngSource, metadata: Metadata, closure: impl FnOnce(Random) -> Result<I>) -> Result<()> {
+    let metadata = metadata.shared();
+
+    for secret in closure(source.stream(0))? {
+        send(&sender, Record::new(secret?, &metadata))?;
+    }
+
+    Ok(())
+}
+
+/// Generate `count` records in parallel, each from its own stream so that a seeded generator produces the same records however they are scheduled.
+///
+/// Records are generated in small batches that are collected in order and sent before the next batch begins,
+/// so they are always written in the same order, the memory used stays constant however many there are,
+/// and generation stops within a batch of the output being closed.
+fn create_records(sender: SyncSender<Record>, source: &RngSource, count: Option<usize>, closure: impl Fn(&mut Random) -> Result<Record> + Send + Sync) -> Result<()> {
+    let total = count.unwrap_or(1);
+    let batch_size = current_num_threads() * BATCH_SIZE_PER_THREAD;
+    let mut start = 0;
+
+    while start < total {
+        let end = total.min(start + batch_size);
+        let records = (start..end)
+            .into_par_iter()
+            .map(|index| closure(&mut source.stream(index as u64)))
+            .collect::<Result<Vec<Record>>>()?;
+
+        for record in records {
+            send(&sender, record)?;
+        }
+
+        start = end;
+    }
+
+    Ok(())
+}
+
+/// Generate `count` secrets in parallel, which all share the same metadata.
+fn create_parallel(sender: SyncSender<Record>, source: &RngSource, count: Option<usize>, metadata: Metadata, closure: impl Fn(&mut Random) -> Result<Secret> + Send + Sync) -> Result<()> {
+    let metadata = metadata.shared();
+
+    create_records(sender, source, count, |rng| Ok(Record::new(closure(rng)?, &metadata)))
+}
+
+/// Describe a secret that encodes `length` random bytes with an alphabet of `alphabet` characters.
+fn encoded_bytes(kind: &'static str, alphabet: usize, length: usize) -> Metadata {
+    Metadata::new(kind, length as f64 * 8.0)
+        .parameter("length", length)
+        .alphabet(alphabet)
+}
 
-    sender.send(buffer).unwrap();
+/// Describe a secret of `length` symbols, each chosen uniformly from `alphabet` of them.
+fn uniform_symbols(kind: &'static str, alphabet: usize, length: usize) -> Metadata {
+    Metadata::new(kind, uniform_entropy(alphabet, length))
+        .parameter("length", length)
+        .alphabet(alphabet)
 }
 
-fn create_parallel(sender: Sender<Vec<u8>>, count: Option<usize>, closure: impl FnOnce() -> Vec<u8> + Send + Sync + Copy) {
-    let max = count.unwrap_or(1);
-    let range = 0..max;
-    let counter = AtomicUsize::new(0);
+/// Stream `length` random bytes in fixed-size chunks, so that outputs of any size use a constant amount of memory.
+pub fn create_bytes(sender: SyncSender<Record>, source: &RngSource, length: u64, progress: bool) -> Result<()> {
+    let metadata = Metadata::new("bytes", length as f64 * 8.0)
+        .parameter("length", length)
+        .alphabet(256)
+        .shared();
+    let mut rng = source.stream(0);
+    let mut progress = Progress::new(progress, length);
+    let mut remaining = length;
+
+    while remaining > 0 {
+        let size = remaining.min(CHUNK_SIZE);
+        let buffer = generate_bytes(size as usize, &mut rng)?;
 
-    range.into_par_iter().for_each(|_| {
-        let mut buffer = closure();
+        send(&sender, Record::new(buffer, &metadata))?;
 
-        if counter.fetch_add(1, Ordering::Relaxed) != max - 1 { buffer.push(LINE_FEED); }
+        progress.advance(size);
 
-        sender.send(buffer).unwrap();
-    });
+        remaining -= size;
+    }
+
+    progress.finish();
+
+    Ok(())
 }
 
-pub fn create_bytes(sender: Sender<Vec<u8>>, length: usize) {
-    create_serial(sender, || generate_bytes(length));
+pub fn create_hex(sender: SyncSender<Record>, source: &RngSource, uppercase: bool, length: usize) -> Result<()> {
+    let metadata = encoded_bytes("hex", 16, length).parameter("uppercase", uppercase);
+
+    create_serial(sender, source, metadata, |rng| generate_hex(uppercase, length, rng))
 }
 
-pub fn create_hex(sender: Sender<Vec<u8>>, uppercase: bool, length: usize) {
-    create_serial(sender, || generate_hex(uppercase, length));
+pub fn create_base64(sender: SyncSender<Record>, source: &RngSource, alphabet: Base64Alphabet, padding: bool, wrap: Option<usize>, length: usize) -> Result<()> {
+    let mut metadata = encoded_bytes("base64", 64, length)
+        .parameter("alphabet", value_name(&alphabet))
+        .parameter("padding", padding);
+
+    if let Some(width) = wrap { metadata = metadata.parameter("wrap", width); }
+
+    create_serial(sender, source, metadata, |rng| generate_base64(alphabet, padding, wrap, length, rng))
 }
 
-pub fn create_base64(sender: Sender<Vec<u8>>, url_safe: bool, length: usize) {
-    create_serial(sender, || generate_base64(url_safe, length));
+pub fn create_base32(sender: SyncSender<Record>, source: &RngSource, lowercase: bool, padding: bool, length: usize) -> Result<()> {
+    let metadata = encoded_bytes("base32", 32, length)
+        .parameter("lowercase", lowercase)
+        .parameter("padding", padding);
+
+    create_serial(sender, source, metadata, |rng| generate_base32(lowercase, padding, length, rng))
 }
 
-pub fn create_password(sender: Sender<Vec<u8>>, character_set: &[char], length: usize, count: Option<usize>) {
-    create_parallel(sender, count, || generate_password(character_set, length));
+pub fn create_crockford(sender: SyncSender<Record>, source: &RngSource, lowercase: bool, length: usize) -> Result<()> {
+    let metadata = encoded_bytes("crockford", 32, length).parameter("lowercase", lowercase);
+
+    create_serial(sender, source, metadata, |rng| generate_crockford(lowercase, length, rng))
 }
 
-pub fn create_passphrase(sender: Sender<Vec<u8>>, wordlist: &[String], separator: &str, length: usize, count: Option<usize>) {
-    create_parallel(sender, count, || generate_passphrase(wordlist, separator, length));
+pub fn create_base58(sender: SyncSender<Record>, source: &RngSource, length: usize) -> Result<()> {
+    create_serial(sender, source, encoded_bytes("base58", 58, length), |rng| generate_base58(length, rng))
 }
 
-pub fn create_username(sender: Sender<Vec<u8>>, capitalize: bool, kind: UsernameKind, length: usize, count: Option<usize>) {
+pub fn create_z85(sender: SyncSender<Record>, source: &RngSource, length: usize) -> Result<()> {
+    create_serial(sender, source, encoded_bytes("z85", 85, length), |rng| generate_z85(length, rng))
+}
+
+pub fn create_base85(sender: SyncSender<Record>, source: &RngSource, length: usize) -> Result<()> {
+    create_serial(sender, source, encoded_bytes("base85", 85, length), |rng| generate_base85(length, rng))
+}
+
+/// Attach the hash of a secret to its record, if a hash was asked for.
+fn hash_record(mut record: Record, hashing: Option<&HashSettings>, rng: &mut Random) -> Result<Record> {
+    let Some(settings) = hashing else { return Ok(record) };
+
+    record.hash = Some(settings.hash(&record.secret, rng)?);
+
+    Ok(record)
+}
+
+pub fn create_password(sender: SyncSender<Record>, source: &RngSource, character_set: &[char], length: usize, hashing: Option<&HashSettings>, count: Option<usize>) -> Result<()> {
+    let metadata = uniform_symbols("password", character_set.len(), length).shared();
+
+    create_records(sender, source, count, |rng| hash_record(Record::new(generate_password(character_set, length, rng)?, &metadata), hashing, rng))
+}
+
+pub fn create_passphrase(sender: SyncSender<Record>, source: &RngSource, wordlist: &[String], separator: &str, length: usize, hashing: Option<&HashSettings>, count: Option<usize>) -> Result<()> {
+    let metadata = uniform_symbols("passphrase", wordlist.len(), length)
+        .parameter("separator", separator)
+        .shared();
+
+    create_records(sender, source, count, |rng| hash_record(Record::new(generate_passphrase(wordlist, separator, length, rng)?, &metadata), hashing, rng))
+}
+
+pub fn create_username(sender: SyncSender<Record>, source: &RngSource, capitalize: bool, kind: UsernameKind, length: usize, count: Option<usize>) -> Result<()> {
+    let metadata = |style: &str, entropy: f64| Metadata::new("username", entropy)
+        .parameter("style", style)
+        .parameter("capitalize", capitalize)
+        .parameter("length", length);
+
     match kind {
-        UsernameKind::Simple => create_parallel(sender, count, || generate_simple_username(capitalize, length)),
-        UsernameKind::Complex => create_parallel(sender, count, || generate_complex_username(capitalize, length))
+        UsernameKind::Simple => create_parallel(sender, source, count, metadata("simple", simple_username_entropy(length)), |rng| generate_simple_username(capitalize, length, rng)),
+        UsernameKind::Complex => create_parallel(sender, source, count, metadata("complex", complex_username_entropy(length)), |rng| generate_complex_username(capitalize, length, rng))
     }
 }
 
-pub fn create_digits(sender: Sender<Vec<u8>>, length: usize, count: Option<usize>) {
-    create_parallel(sender, count, || generate_digits(length));
+pub fn create_digits(sender: SyncSender<Record>, source: &RngSource, prefix: &str, checksum: Option<Checksum>, length: usize, count: Option<usize>) -> Result<()> {
+    let random = length.saturating_sub(prefix.len() + checksum.map_or(0, |checksum| checksum.length()));
+    let mut metadata = Metadata::new("digits", uniform_entropy(10, random))
+        .parameter("length", length)
+        .alphabet(10);
+
+    if !prefix.is_empty() { metadata = metadata.parameter("prefix", prefix); }
+    if let Some(checksum) = checksum { metadata = metadata.parameter("checksum", value_name(&checksum)); }
+
+    create_parallel(sender, source, count, metadata, |rng| generate_checked_digits(prefix, checksum, length, rng))
+}
+
+pub fn create_secure_pin(sender: SyncSender<Record>, source: &RngSource, weak_pins: &HashSet<Vec<u8>>, length: usize, count: Option<usize>) -> Result<()> {
+    let metadata = Metadata::new("digits", effective_entropy(length, weak_pins.len()))
+        .parameter("length", length)
+        .parameter("secure_pin", true)
+        .alphabet(10);
+
+    create_parallel(sender, source, count, metadata, |rng| generate_secure_pin(weak_pins, length, rng))
+}
+
+/// Describe numbers chosen from a range, whose bounds and step are integers scaled by `10^precision`.
+/// Describe numbers drawn from a range, or the given number of unique numbers drawn from it without replacement.
+fn number_metadata(minimum: &BigInt, maximum: &BigInt, step: &BigInt, precision: u32, radix: u32, unique: Option<usize>) -> Result<Metadata> {
+    let size = range_size(minimum, maximum, step, precision)?;
+    let entropy = match unique {
+        Some(count) => drawn_entropy(&size, count),
+        None => log2(&size)
+    };
+
+    let metadata = Metadata::new("number", entropy)
+        .parameter("minimum", format_number(minimum, precision, 10))
+        .parameter("maximum", format_number(maximum, precision, 10))
+        .parameter("step", format_number(step, precision, 10))
+        .parameter("precision", precision)
+        .parameter("radix", radix)
+        .parameter("unique", unique.is_some())
+        .alphabet(size);
+
+    Ok(metadata)
 }
 
-pub fn create_number(sender: Sender<Vec<u8>>, minimum: usize, maximum: usize, count: Option<usize>) {
-    create_parallel(sender, count, || generate_number(minimum, maximum));
+#[allow(clippy::too_many_arguments)]
+pub fn create_number(sender: SyncSender<Record>, source: &RngSource, minimum: &BigInt, maximum: &BigInt, step: &BigInt, precision: u32, radix: u32, count: Option<usize>) -> Result<()> {
+    let metadata = number_metadata(minimum, maximum, step, precision, radix, None)?;
+
+    create_parallel(sender, source, count, metadata, |rng| generate_number(minimum, maximum, step, precision, radix, rng))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_unique_number(sender: SyncSender<Record>, source: &RngSource, minimum: &BigInt, maximum: &BigInt, step: &BigInt, precision: u32, radix: u32, count: Option<usize>) -> Result<()> {
+    let metadata = number_metadata(minimum, maximum, step, precision, radix, Some(count.unwrap_or(1)))?;
+
+    create_sequence(sender, source, metadata, |mut rng| Ok(generate_unique_numbers(minimum, maximum, step, precision, radix, count.unwrap_or(1), &mut rng)?.map(Ok)))
+}
+
+pub fn create_permutation(sender: SyncSender<Record>, source: &RngSource, start: &BigInt, length: usize) -> Result<()> {
+    let metadata = Metadata::new("permutation", drawn_entropy(&BigInt::from(length), length))
+        .parameter("start", start.to_string())
+        .parameter("length", length)
+        .alphabet(length);
+
+    create_sequence(sender, source, metadata, |mut rng| Ok(generate_permutation(start, length, &mut rng)?.map(Ok)))
+}
+
+pub fn create_dice(sender: SyncSender<Record>, source: &RngSource, dice: &DiceNotation, count: Option<usize>) -> Result<()> {
+    let metadata = Metadata::new("dice", dice.entropy())
+        .parameter("notation", dice.to_string())
+        .alphabet(dice.sides());
+
+    create_parallel(sender, source, count, metadata, |rng| generate_dice(dice, rng))
+}
+
+pub fn create_coin(sender: SyncSender<Record>, source: &RngSource, count: Option<usize>) -> Result<()> {
+    create_parallel(sender, source, count, Metadata::new("coin", 1.0).alphabet(2), generate_coin)
+}
+
+pub fn create_cards(sender: SyncSender<Record>, source: &RngSource, decks: usize, draw: Option<usize>) -> Result<()> {
+    // The shoe is checked before it is described, so that an impossible number of decks fails before any work is done.
+    let size = shoe_size(decks)?;
+    let drawn = draw.unwrap_or(size).min(size);
+    let mut metadata = Metadata::new("cards", drawn_entropy(&BigInt::from(size), drawn))
+        .parameter("decks", decks)
+        .alphabet(size);
+
+    if let Some(draw) = draw { metadata = metadata.parameter("draw", draw); }
+
+    create_sequence(sender, source, metadata, |mut rng| Ok(generate_cards(decks, draw, &mut rng)?.into_iter().map(Ok)))
+}
+
+/// Send a private key to the output, and print its public key and fingerprint on STDERR so that they never mix with the secret.
+pub fn create_keypair(sender: SyncSender<Record>, source: &RngSource, kind: KeyType, format: KeyFormat) -> Result<()> {
+    let metadata = Metadata::new("keypair", kind.entropy() as f64)
+        .parameter("type", value_name(&kind))
+        .parameter("format", value_name(&format));
+
+    create_serial(sender, source, metadata, |rng| {
+        let keypair = generate_keypair(kind, format, rng)?;
+
+        writeln!(stderr(), "{}", keypair.public.trim_end())?;
+
+        info!("The fingerprint of the public key is {}", keypair.fingerprint);
+
+        Ok(keypair.private)
+    })
+}
+
+pub fn create_uuid(sender: SyncSender<Record>, source: &RngSource, version: UuidVersion, monotonic: bool, count: Option<usize>) -> Result<()> {
+    let metadata = Metadata::new("uuid", version.random_bits() as f64)
+        .parameter("version", value_name(&version))
+        .parameter("monotonic", monotonic);
+
+    // Monotonic identifiers are generated in order on a single thread, since their order is the point.
+    if monotonic { create_sequence(sender, source, metadata, |rng| Ok(generate_monotonic_uuids(count.unwrap_or(1), rng))) }
+    else { create_parallel(sender, source, count, metadata, |rng| generate_uuid(version, rng)) }
+}
+
+pub fn create_ulid(sender: SyncSender<Record>, source: &RngSource, monotonic: bool, count: Option<usize>) -> Result<()> {
+    let metadata = Metadata::new("ulid", ULID_RANDOM_BITS as f64).parameter("monotonic", monotonic);
+
+    if monotonic { create_sequence(sender, source, metadata, |rng| Ok(generate_monotonic_ulids(count.unwrap_or(1), rng))) }
+    else { create_parallel(sender, source, count, metadata, generate_ulid) }
+}
+
+pub fn create_nanoid(sender: SyncSender<Record>, source: &RngSource, alphabet: &[char], size: usize, count: Option<usize>) -> Result<()> {
+    let metadata = Metadata::new("nanoid", uniform_entropy(alphabet.len(), size))
+        .parameter("size", size)
+        .parameter("alphabet", alphabet.iter().collect::<String>())
+        .alphabet(alphabet.len());
+
+    create_parallel(sender, source, count, metadata, |rng| generate_nanoid(alphabet, size, rng))
+}
+
+pub fn create_ksuid(sender: SyncSender<Record>, source: &RngSource, count: Option<usize>) -> Result<()> {
+    create_parallel(sender, source, count, Metadata::new("ksuid", KSUID_RANDOM_BITS as f64), generate_ksuid)
+}
+
+/// Send a one-time password secret and its otpauth URI to the output, and optionally save the URI as a QR code.
+#[allow(clippy::too_many_arguments)]
+pub fn create_otp(sender: SyncSender<Record>, source: &RngSource, kind: OtpKind, issuer: Option<&str>, account: &str, algorithm: OtpAlgorithm, digits: u32, qr_code: Option<SecureFile>) -> Result<()> {
+    let entropy = algorithm.secret_length() as f64 * 8.0;
+    let describe = |name: &'static str| {
+        let mut metadata = Metadata::new(name, entropy);
+
+        if let Some(issuer) = issuer { metadata = metadata.parameter("issuer", issuer); }
+
+        metadata = metadata
+            .parameter("account", account)
+            .parameter("algorithm", value_name(&algorithm))
+            .parameter("digits", digits);
+
+        match kind {
+            OtpKind::Totp { period } => metadata.parameter("period", period),
+            OtpKind::Hotp { counter } => metadata.parameter("counter", counter)
+        }
+    };
+    let (secret_kind, uri_kind) = match kind {
+        OtpKind::Totp { .. } => ("totp", "totp-uri"),
+        OtpKind::Hotp { .. } => ("hotp", "hotp-uri")
+    };
+    let otp = generate_otp(kind, issuer, account, algorithm, digits, &mut source.stream(0))?;
+
+    if let Some(mut file) = qr_code {
+        render_qr_code(&mut file, &otp.uri)?;
+
+        file.commit()?;
+    }
+
+    // The secret and the URI that contains it are separate records, since they are described differently.
+    send(&sender, Record::new(otp.secret, &describe(secret_kind).alphabet(32).shared()))?;
+    send(&sender, Record::new(otp.uri, &describe(uri_kind).shared()))
+}
+
+pub fn create_token(sender: SyncSender<Record>, source: &RngSource, prefix: &str, length: usize, checksum: TokenChecksum, count: Option<usize>) -> Result<()> {
+    let metadata = encoded_bytes("token", 62, length)
+        .parameter("prefix", prefix)
+        .parameter("checksum", value_name(&checksum));
+
+    create_parallel(sender, source, count, metadata, |rng| generate_token(prefix, length, checksum, rng))
+}
+
+pub fn create_mac(sender: SyncSender<Record>, source: &RngSource, style: MacStyle, uppercase: bool, count: Option<usize>) -> Result<()> {
+    let metadata = Metadata::new("mac", MAC_RANDOM_BITS as f64)
+        .parameter("style", value_name(&style))
+        .parameter("uppercase", uppercase);
+
+    create_parallel(sender, source, count, metadata, |rng| generate_mac(style, uppercase, rng))
+}
+
+pub fn create_ula(sender: SyncSender<Record>, source: &RngSource, count: Option<usize>) -> Result<()> {
+    create_parallel(sender, source, count, Metadata::new("ula", ULA_RANDOM_BITS as f64), generate_ula)
+}
+
+pub fn create_ip(sender: SyncSender<Record>, source: &RngSource, cidr: &Cidr, count: Option<usize>) -> Result<()> {
+    let metadata = Metadata::new("ip", cidr.entropy()).parameter("cidr", cidr.to_string());
+
+    create_parallel(sender, source, count, metadata, |rng| generate_ip(cidr, rng))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::sync_channel;
+    use std::thread;
+
+    use rand::RngCore;
+    use rayon::ThreadPoolBuilder;
+
+    use super::*;
+
+    fn numbered_records(sender: SyncSender<Record>, source: &RngSource, count: usize) -> Result<()> {
+        let metadata = Metadata::new("test", 64.0).shared();
+
+        create_records(sender, source, Some(count), |rng| Ok(Record::new(Secret::from(rng.next_u64().to_string()), &metadata)))
+    }
+
+    #[test]
+    fn keeps_records_in_order() {
+        let source = RngSource::new(None, Some(b"order"), None).unwrap();
+        let (sender, receiver) = sync_channel(CHANNEL_CAPACITY);
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+        let received = thread::scope(|scope| {
+            scope.spawn(|| pool.install(|| numbered_records(sender, &source, 1000)).unwrap());
+
+            receiver.iter().map(|record| record.secret).collect::<Vec<Secret>>()
+        });
+        let expected = (0..1000).map(|index| Secret::from(source.stream(index).next_u64().to_string())).collect::<Vec<Secret>>();
+
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn stops_when_output_closes() {
+        let source = RngSource::new(None, Some(b"closed"), None).unwrap();
+        let (sender, receiver) = sync_channel(CHANNEL_CAPACITY);
+
+        drop(receiver);
+
+        assert!(matches!(numbered_records(sender, &source, usize::MAX), Err(Error::BrokenPipe)));
+    }
 }
@@ -1,60 +1,134 @@
-use base64::Engine;
-use rand::{Rng, thread_rng, distributions::Uniform};
+use base64::{alphabet, Engine};
+use clap::ValueEnum;
+use rand::Rng;
 use hex::{encode, encode_upper};
-use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+use base64::engine::{GeneralPurpose, GeneralPurposeConfig};
+
+use crate::encoding::{encode_ascii85, encode_base32, encode_radix, encode_z85, wrap_lines, BASE32, BASE58, CROCKFORD};
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// The alphabets that Base64 strings can be encoded with.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Base64Alphabet {
+    /// The standard alphabet from RFC 4648
+    Standard,
+    /// The URL-safe alphabet from RFC 4648
+    Url,
+    /// The alphabet used by bcrypt
+    Bcrypt,
+    /// The alphabet used by crypt(3)
+    Crypt,
+    /// The alphabet used by IMAP to encode mailbox names
+    Imap
+}
+
+impl Base64Alphabet {
+    fn to_alphabet(self) -> alphabet::Alphabet {
+        match self {
+            Base64Alphabet::Standard => alphabet::STANDARD,
+            Base64Alphabet::Url => alphabet::URL_SAFE,
+            Base64Alphabet::Bcrypt => alphabet::BCRYPT,
+            Base64Alphabet::Crypt => alphabet::CRYPT,
+            Base64Alphabet::Imap => alphabet::IMAP_MUTF7
+        }
+    }
+}
+
+pub fn generate_bytes<R: Rng>(length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
+
+    let mut bytes = Secret::from(vec![0_u8; length]);
+
+    rng.fill_bytes(&mut bytes);
+
+    Ok(bytes)
+}
+
+pub fn generate_hex<R: Rng>(uppercase: bool, length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
+
+    let bytes = generate_bytes(length, rng)?;
+
+    if uppercase { Ok(Secret::from(encode_upper(bytes))) }
+    else { Ok(Secret::from(encode(bytes))) }
+}
+
+/// Generate random bytes and encode them as Base64, optionally wrapping lines after `wrap` characters.
+pub fn generate_base64<R: Rng>(alphabet: Base64Alphabet, padding: bool, wrap: Option<usize>, length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
 
-pub fn generate_bytes(length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
+    let bytes = generate_bytes(length, rng)?;
+    let configuration = GeneralPurposeConfig::new().with_encode_padding(padding);
+    let string = Secret::from(GeneralPurpose::new(&alphabet.to_alphabet(), configuration).encode(bytes));
 
-    let range = Uniform::new_inclusive(u8::MIN, u8::MAX);
+    match wrap {
+        Some(width) => Ok(wrap_lines(&string, width)),
+        None => Ok(string)
+    }
+}
+
+pub fn generate_base32<R: Rng>(lowercase: bool, padding: bool, length: usize, rng: &mut R) -> Result<Secret> {
+    let bytes = generate_bytes(length, rng)?;
+    let mut string = encode_base32(&bytes, BASE32, padding);
+
+    if lowercase { string.make_ascii_lowercase(); }
 
-    thread_rng()
-        .sample_iter(range)
-        .take(length)
-        .collect()
+    Ok(string)
 }
 
-pub fn generate_hex(uppercase: bool, length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
+pub fn generate_crockford<R: Rng>(lowercase: bool, length: usize, rng: &mut R) -> Result<Secret> {
+    let bytes = generate_bytes(length, rng)?;
+    let mut string = encode_base32(&bytes, CROCKFORD, false);
+
+    if lowercase { string.make_ascii_lowercase(); }
+
+    Ok(string)
+}
 
-    let bytes = generate_bytes(length);
+pub fn generate_base58<R: Rng>(length: usize, rng: &mut R) -> Result<Secret> {
+    let bytes = generate_bytes(length, rng)?;
 
-    if uppercase { encode_upper(bytes).into_bytes() }
-    else { encode(bytes).into_bytes() }
+    Ok(encode_radix(&bytes, BASE58))
 }
 
-pub fn generate_base64(url_safe: bool, length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
+pub fn generate_z85<R: Rng>(length: usize, rng: &mut R) -> Result<Secret> {
+    let bytes = generate_bytes(length, rng)?;
+    encode_z85(&bytes)
+        .ok_or_else(|| Error::InvalidArgument(format!("Z85 can only encode a multiple of four bytes, but {length} bytes were requested")))
+}
 
-    let bytes = generate_bytes(length);
+pub fn generate_base85<R: Rng>(length: usize, rng: &mut R) -> Result<Secret> {
+    let bytes = generate_bytes(length, rng)?;
 
-    if url_safe { URL_SAFE.encode(bytes).into_bytes() }
-    else { STANDARD.encode(bytes).into_bytes() }
+    Ok(encode_ascii85(&bytes))
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::from_utf8;
 
+    use rand::thread_rng;
+
     use super::*;
 
     #[test]
     fn generates_one_kilobyte() {
-        let bytes = generate_bytes(1024);
+        let bytes = generate_bytes(1024, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 1024)
     }
 
     #[test]
     fn generates_zero_bytes() {
-        let bytes = generate_bytes(0);
+        let bytes = generate_bytes(0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
 
     #[test]
     fn generates_one_kilobyte_as_hex() {
-        let bytes = generate_hex(false, 1024);
+        let bytes = generate_hex(false, 1024, &mut thread_rng()).unwrap();
         let string = from_utf8(&bytes).unwrap();
 
         assert_eq!(string.chars().count(), 2048)
@@ -62,22 +136,78 @@ mod tests {
 
     #[test]
     fn generates_zero_bytes_as_hex() {
-        let bytes = generate_hex(false, 0);
+        let bytes = generate_hex(false, 0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
 
     #[test]
     fn generates_one_kilobyte_as_base64() {
-        let bytes = generate_base64(false, 1024);
+        let bytes = generate_base64(Base64Alphabet::Standard, true, None, 1024, &mut thread_rng()).unwrap();
         let string = from_utf8(&bytes).unwrap();
 
         assert_eq!(string.chars().count(), 1368)
     }
 
+    #[test]
+    fn generates_one_kilobyte_as_unpadded_base64() {
+        let bytes = generate_base64(Base64Alphabet::Url, false, None, 1024, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), 1366)
+    }
+
+    #[test]
+    fn generates_wrapped_base64() {
+        let bytes = generate_base64(Base64Alphabet::Standard, true, Some(76), 1024, &mut thread_rng()).unwrap();
+        let string = from_utf8(&bytes).unwrap();
+
+        assert!(string.lines().all(|line| line.len() <= 76));
+        assert_eq!(string.lines().count(), 18)
+    }
+
+    #[test]
+    fn generates_base64_with_crypt_alphabet() {
+        let bytes = generate_base64(Base64Alphabet::Crypt, false, None, 1024, &mut thread_rng()).unwrap();
+
+        assert!(bytes.iter().all(|byte| byte.is_ascii_alphanumeric() || *byte == b'.' || *byte == b'/'))
+    }
+
+    #[test]
+    fn generates_one_kilobyte_as_base32() {
+        let bytes = generate_base32(false, true, 1024, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), 1640)
+    }
+
+    #[test]
+    fn generates_one_kilobyte_as_unpadded_base32() {
+        let bytes = generate_base32(false, false, 1024, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), 1639)
+    }
+
+    #[test]
+    fn generates_lowercase_crockford_base32() {
+        let bytes = generate_crockford(true, 1024, &mut thread_rng()).unwrap();
+
+        assert!(bytes.iter().all(|byte| byte.is_ascii_digit() || byte.is_ascii_lowercase()))
+    }
+
+    #[test]
+    fn generates_one_kilobyte_as_z85() {
+        let bytes = generate_z85(1024, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), 1280)
+    }
+
+    #[test]
+    fn rejects_z85_of_partial_blocks() {
+        assert!(generate_z85(1023, &mut thread_rng()).is_err())
+    }
+
     #[test]
     fn generates_zero_bytes_as_base64() {
-        let bytes = generate_base64(false, 0);
+        let bytes = generate_base64(Base64Alphabet::Standard, true, None, 0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
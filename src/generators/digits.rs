@@ -1,36 +1,118 @@
-use rand::{distributions::Uniform, thread_rng, Rng};
+use std::collections::HashSet;
 
-pub fn generate_digits(length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
+use rand::{distributions::Uniform, Rng};
 
-    let distribution = Uniform::new_inclusive(0, 9);
+use crate::checksum::Checksum;
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+pub fn generate_digits<R: Rng>(length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
 
-    thread_rng()
+    let distribution = Uniform::new_inclusive(0, 9);
+    let digits = rng
         .sample_iter(distribution)
         .take(length)
-        .map(|value| char::from_digit(value, 10).unwrap())
-        .collect::<String>()
-        .into_bytes()
+        .map(|value| b'0' + value)
+        .collect();
+
+    Ok(digits)
+}
+
+/// Generate a sequence of `length` digits that begins with `prefix`, and optionally ends with valid check digits.
+pub fn generate_checked_digits<R: Rng>(prefix: &str, checksum: Option<Checksum>, length: usize, rng: &mut R) -> Result<Secret> {
+    let check_length = checksum.map_or(0, |checksum| checksum.length());
+
+    if !prefix.bytes().all(|byte| byte.is_ascii_digit()) { return Err(Error::InvalidArgument(format!("The prefix {prefix} contains characters that are not digits"))); }
+
+    if prefix.len() + check_length > length { return Err(Error::PolicyInfeasible(format!("{length} digits are too few to fit the prefix and check digits"))); }
+
+    let mut output = Secret::with_capacity(length);
+
+    output.extend_from_slice(prefix.as_bytes());
+    output.extend_from_slice(&generate_digits(length - prefix.len() - check_length, rng)?);
+
+    if let Some(checksum) = checksum {
+        let check = checksum.compute(&output);
+
+        output.extend_from_slice(&check);
+    }
+
+    Ok(output)
+}
+
+/// Generate a PIN of `length` digits that is not one of the `weak_pins`.
+pub fn generate_secure_pin<R: Rng>(weak_pins: &HashSet<Vec<u8>>, length: usize, rng: &mut R) -> Result<Secret> {
+    let total = 10_u64.checked_pow(length as u32).unwrap_or(u64::MAX);
+
+    if weak_pins.len() as u64 >= total { return Err(Error::PolicyInfeasible(format!("Every PIN of {length} digits is excluded"))); }
+
+    loop {
+        let pin = generate_digits(length, rng)?;
+
+        if !weak_pins.contains(&*pin) { return Ok(pin); }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::from_utf8;
 
+    use rand::thread_rng;
+
     use super::*;
 
     #[test]
     fn generates_ten_thousand_digits() {
-        let bytes = generate_digits(10000);
+        let bytes = generate_digits(10000, &mut thread_rng()).unwrap();
         let string = from_utf8(&bytes).unwrap();
 
         assert_eq!(string.chars().count(), 10000)
     }
 
+    #[test]
+    fn generates_prefixed_luhn_digits() {
+        let bytes = generate_checked_digits("4", Some(Checksum::Luhn), 16, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(bytes[0], b'4');
+        assert!(Checksum::Luhn.validate(&bytes))
+    }
+
+    #[test]
+    fn generates_mod97_digits() {
+        let bytes = generate_checked_digits("", Some(Checksum::Mod97), 20, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), 20);
+        assert!(Checksum::Mod97.validate(&bytes))
+    }
+
+    #[test]
+    fn generates_secure_pin() {
+        let weak_pins = (0..9999).map(|value| format!("{value:04}").into_bytes()).collect::<HashSet<_>>();
+        let bytes = generate_secure_pin(&weak_pins, 4, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes, b"9999")
+    }
+
+    #[test]
+    fn rejects_digits_too_short_for_check_digits() {
+        let result = generate_checked_digits("12", Some(Checksum::Mod97), 3, &mut thread_rng());
+
+        assert!(matches!(result, Err(Error::PolicyInfeasible(_))))
+    }
+
+    #[test]
+    fn rejects_secure_pin_when_every_pin_is_weak() {
+        let weak_pins = (0..10000).map(|value| format!("{value:04}").into_bytes()).collect::<HashSet<_>>();
+        let result = generate_secure_pin(&weak_pins, 4, &mut thread_rng());
+
+        assert!(matches!(result, Err(Error::PolicyInfeasible(_))))
+    }
 
     #[test]
     fn generates_zero_digits() {
-        let bytes = generate_digits(0);
+        let bytes = generate_digits(0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
@@ -0,0 +1,211 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+const RANKS: [&str; 13] = [
+    "A", "2", "3", "4", "5", "6", "7",
+    "8", "9", "10", "J", "Q", "K"
+];
+
+const SUITS: [char; 4] = ['♠', '♥', '♦', '♣'];
+
+/// The number of distinct cards in a standard deck.
+pub const DECK_SIZE: usize = RANKS.len() * SUITS.len();
+
+/// The most decks that can be shuffled together, which is far more than any game uses.
+const MAXIMUM_DECKS: usize = 1000;
+
+/// The most dice that can be rolled at once, each of which is written out along with the total.
+const MAXIMUM_DICE: usize = 10_000;
+
+/// A roll of dice in standard dice notation, such as `3d6+2`.
+#[derive(Clone)]
+pub struct DiceNotation {
+    count: usize,
+    sides: u64,
+    modifier: i64
+}
+
+impl FromStr for DiceNotation {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("{value} is not valid dice notation, such as 3d6+2");
+        let lowercase = value.trim().to_ascii_lowercase();
+        let (count, rest) = lowercase.split_once('d').ok_or_else(invalid)?;
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(index) => rest.split_at(index),
+            None => (rest, "")
+        };
+
+        let count = if count.is_empty() { 1 } else { count.parse::<usize>().map_err(|_| invalid())? };
+        let sides = if sides == "%" { 100 } else { sides.parse::<u64>().map_err(|_| invalid())? };
+        let modifier = if modifier.is_empty() { 0 } else { modifier.parse::<i64>().map_err(|_| invalid())? };
+
+        if count == 0 || sides == 0 { return Err(invalid()); }
+
+        Ok(DiceNotation { count, sides, modifier })
+    }
+}
+
+impl DiceNotation {
+    pub fn sides(&self) -> u64 {
+        self.sides
+    }
+
+    /// The number of bits of entropy in the individual rolls, which is more than in their total.
+    pub fn entropy(&self) -> f64 {
+        self.count as f64 * (self.sides as f64).log2()
+    }
+}
+
+impl fmt::Display for DiceNotation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}d{}", self.count, self.sides)?;
+
+        match self.modifier.cmp(&0) {
+            Ordering::Greater => write!(formatter, "+{}", self.modifier),
+            Ordering::Less => write!(formatter, "-{}", self.modifier.unsigned_abs()),
+            Ordering::Equal => Ok(())
+        }
+    }
+}
+
+/// Roll dice and describe every individual roll along with the total, such as `3d6+2: [4, 1, 6] + 2 = 13`.
+pub fn generate_dice<R: Rng>(dice: &DiceNotation, rng: &mut R) -> Result<Secret> {
+    if dice.count > MAXIMUM_DICE { return Err(Error::PolicyInfeasible(format!("Cannot roll more than {MAXIMUM_DICE} dice at once"))); }
+
+    let rolls = (0..dice.count)
+        .map(|_| rng.gen_range(1..=dice.sides))
+        .collect::<Vec<u64>>();
+    let total = rolls.iter().map(|roll| *roll as i128).sum::<i128>() + dice.modifier as i128;
+    let breakdown = rolls
+        .iter()
+        .map(|roll| roll.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+    let modifier = match dice.modifier.cmp(&0) {
+        Ordering::Greater => format!(" + {}", dice.modifier),
+        Ordering::Less => format!(" - {}", dice.modifier.unsigned_abs()),
+        Ordering::Equal => String::new()
+    };
+
+    Ok(format!("{dice}: [{breakdown}]{modifier} = {total}").into())
+}
+
+/// Flip a fair coin.
+pub fn generate_coin<R: Rng>(rng: &mut R) -> Result<Secret> {
+    let heads = rng.gen_bool(1.0 / 2.0);
+
+    if heads { Ok(Secret::from(b"heads".as_slice())) }
+    else { Ok(Secret::from(b"tails".as_slice())) }
+}
+
+/// The number of cards in a shoe of `decks` standard decks, which can hold at most [`MAXIMUM_DECKS`] of them.
+pub fn shoe_size(decks: usize) -> Result<usize> {
+    decks
+        .checked_mul(DECK_SIZE)
+        .filter(|_| decks <= MAXIMUM_DECKS)
+        .ok_or_else(|| Error::PolicyInfeasible(format!("Cannot shuffle more than {MAXIMUM_DECKS} decks together")))
+}
+
+/// Shuffle `decks` standard decks of playing cards together, and draw `draw` cards from the top.
+pub fn generate_cards<R: Rng>(decks: usize, draw: Option<usize>, rng: &mut R) -> Result<Vec<Secret>> {
+    let size = shoe_size(decks)?;
+    let draw = draw.unwrap_or(size);
+
+    if draw > size { return Err(Error::PolicyInfeasible(format!("Cannot draw {draw} cards from a shoe of only {size} cards"))); }
+
+    let mut shoe = (0..decks)
+        .flat_map(|_| SUITS.iter().flat_map(|suit| RANKS.iter().map(move |rank| format!("{rank}{suit}"))))
+        .collect::<Vec<String>>();
+    let (hand, _) = shoe.partial_shuffle(rng, draw);
+
+    let cards = hand.iter()
+        .map(|card| card.clone().into())
+        .collect();
+
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::str::from_utf8;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn parses_dice_notation() {
+        assert_eq!("3d6+2".parse::<DiceNotation>().unwrap().to_string(), "3d6+2");
+        assert_eq!("d20".parse::<DiceNotation>().unwrap().to_string(), "1d20");
+        assert_eq!("4D6-1".parse::<DiceNotation>().unwrap().to_string(), "4d6-1");
+        assert_eq!("2d%".parse::<DiceNotation>().unwrap().to_string(), "2d100");
+    }
+
+    #[test]
+    fn rejects_invalid_dice_notation() {
+        for value in ["", "3", "3d", "0d6", "3d0", "3d6+", "3x6"] {
+            assert!(value.parse::<DiceNotation>().is_err(), "expected {value} to be rejected");
+        }
+    }
+
+    #[test]
+    fn rolls_dice_within_range() {
+        let dice = "10d6+2".parse::<DiceNotation>().unwrap();
+        let bytes = generate_dice(&dice, &mut thread_rng()).unwrap();
+        let string = from_utf8(&bytes).unwrap();
+        let (_, total) = string.rsplit_once(" = ").unwrap();
+        let total = total.parse::<i128>().unwrap();
+
+        assert!(string.starts_with("10d6+2: ["));
+        assert!((12..=62).contains(&total), "expected a total in 12..=62, but got {total} instead");
+    }
+
+    #[test]
+    fn flips_coin() {
+        let bytes = generate_coin(&mut thread_rng()).unwrap();
+
+        assert!(bytes == b"heads" || bytes == b"tails")
+    }
+
+    #[test]
+    fn draws_distinct_cards_from_one_deck() {
+        let cards = generate_cards(1, None, &mut thread_rng()).unwrap();
+        let unique = cards.iter().collect::<HashSet<_>>();
+
+        assert_eq!(unique.len(), 52)
+    }
+
+    #[test]
+    fn draws_from_multiple_decks() {
+        let cards = generate_cards(2, Some(104), &mut thread_rng()).unwrap();
+        let unique = cards.iter().collect::<HashSet<_>>();
+
+        assert_eq!(cards.len(), 104);
+        assert_eq!(unique.len(), 52)
+    }
+
+    #[test]
+    fn rejects_drawing_too_many_cards() {
+        let result = generate_cards(1, Some(53), &mut thread_rng());
+
+        assert!(matches!(result, Err(Error::PolicyInfeasible(_))))
+    }
+
+    #[test]
+    fn rejects_too_many_decks_or_dice() {
+        let dice = format!("{}d6", MAXIMUM_DICE + 1).parse::<DiceNotation>().unwrap();
+
+        assert!(matches!(generate_cards(usize::MAX, Some(1), &mut thread_rng()), Err(Error::PolicyInfeasible(_))));
+        assert!(matches!(generate_cards(MAXIMUM_DECKS + 1, Some(1), &mut thread_rng()), Err(Error::PolicyInfeasible(_))));
+        assert!(matches!(generate_dice(&dice, &mut thread_rng()), Err(Error::PolicyInfeasible(_))));
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use rand::{distributions::Slice, Rng};
+
+use super::binary::generate_bytes;
+use crate::encoding::{encode_fixed_radix, BASE62, CROCKFORD};
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// The alphabet used by NanoID by default, which is safe to use in URLs and filenames.
+pub const NANOID_ALPHABET: &str = "_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// The number of random bits in a UUIDv7, after the timestamp, version and variant.
+pub const UUID_RANDOM_BITS: u32 = 74;
+
+/// The number of random bits in a ULID, after the timestamp.
+pub const ULID_RANDOM_BITS: u32 = 80;
+
+/// The number of random bits in a KSUID, after the timestamp.
+pub const KSUID_RANDOM_BITS: u32 = 128;
+
+/// The start of the KSUID epoch, in seconds since the Unix epoch.
+const KSUID_EPOCH: u64 = 1_400_000_000;
+
+/// The length of every KSUID, which is padded with leading zeros.
+const KSUID_LENGTH: usize = 27;
+
+/// The versions of UUID that can be generated.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UuidVersion {
+    /// Entirely random, apart from the version and variant
+    #[value(name = "4")]
+    V4,
+    /// Prefixed with a millisecond timestamp, so that identifiers sort in the order in which they were created
+    #[value(name = "7")]
+    V7
+}
+
+impl UuidVersion {
+    /// The number of random bits in a UUID of this version.
+    pub fn random_bits(self) -> u32 {
+        match self {
+            UuidVersion::V4 => 122,
+            UuidVersion::V7 => UUID_RANDOM_BITS
+        }
+    }
+}
+
+/// Keeps time-ordered identifiers that are generated within the same millisecond in order, by incrementing the random bits of the previous identifier.
+struct Monotonic {
+    bits: u32,
+    previous: Option<(u64, u128)>
+}
+
+impl Monotonic {
+    fn new(bits: u32) -> Self {
+        Monotonic { bits, previous: None }
+    }
+
+    fn next<R: Rng>(&mut self, rng: &mut R) -> Result<(u64, u128)> {
+        let now = unix_time()?.as_millis() as u64;
+        let maximum = (1_u128 << self.bits) - 1;
+
+        let next = match self.previous {
+            Some((timestamp, random)) if timestamp >= now && random < maximum => (timestamp, random + 1),
+            // Borrow the next millisecond rather than overflow, which keeps the order even if the clock goes backwards.
+            Some((timestamp, _)) if timestamp >= now => (timestamp + 1, random_bits(self.bits, rng)),
+            _ => (now, random_bits(self.bits, rng))
+        };
+
+        self.previous = Some(next);
+
+        Ok(next)
+    }
+}
+
+fn unix_time() -> Result<std::time::Duration> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::Internal("The system clock is set before 1970".into()))
+}
+
+fn random_bits<R: Rng>(bits: u32, rng: &mut R) -> u128 {
+    rng.gen::<u128>() >> (128 - bits)
+}
+
+fn format_uuid(value: u128) -> String {
+    let hex = format!("{value:032x}");
+
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+/// Lay out a UUIDv7 from a millisecond timestamp and 74 random bits.
+fn uuid_v7(timestamp: u64, random: u128) -> String {
+    let random_a = random >> 62;
+    let random_b = random & ((1 << 62) - 1);
+    let value = ((timestamp as u128 & 0xFFFF_FFFF_FFFF) << 80) | (0x7 << 76) | (random_a << 64) | (0b10 << 62) | random_b;
+
+    format_uuid(value)
+}
+
+/// Encode a ULID from a millisecond timestamp and 80 random bits, as 26 Crockford Base32 characters.
+fn ulid(timestamp: u64, random: u128) -> String {
+    let value = ((timestamp as u128 & 0xFFFF_FFFF_FFFF) << 80) | random;
+
+    (0..26)
+        .map(|index| CROCKFORD[((value >> (125 - index * 5)) & 0x1F) as usize] as char)
+        .collect()
+}
+
+/// Generate a UUID with the correct version and variant bits, as described by RFC 9562.
+pub fn generate_uuid<R: Rng>(version: UuidVersion, rng: &mut R) -> Result<Secret> {
+    match version {
+        UuidVersion::V4 => {
+            let mut bytes = generate_bytes(16, rng)?;
+
+            bytes[6] = (bytes[6] & 0x0F) | 0x40;
+            bytes[8] = (bytes[8] & 0x3F) | 0x80;
+
+            let value = bytes.iter().fold(0_u128, |value, byte| (value << 8) | *byte as u128);
+
+            Ok(format_uuid(value).into())
+        }
+        UuidVersion::V7 => {
+            let timestamp = unix_time()?.as_millis() as u64;
+
+            Ok(uuid_v7(timestamp, random_bits(UUID_RANDOM_BITS, rng)).into())
+        }
+    }
+}
+
+/// Generate `count` version 7 UUIDs one at a time, each strictly greater than the one before it, even within the same millisecond.
+pub fn generate_monotonic_uuids<R: Rng>(count: usize, mut rng: R) -> impl Iterator<Item = Result<Secret>> {
+    let mut monotonic = Monotonic::new(UUID_RANDOM_BITS);
+
+    (0..count).map(move |_| monotonic.next(&mut rng).map(|(timestamp, random)| uuid_v7(timestamp, random).into()))
+}
+
+/// Generate a Universally Unique Lexicographically Sortable Identifier.
+pub fn generate_ulid<R: Rng>(rng: &mut R) -> Result<Secret> {
+    let timestamp = unix_time()?.as_millis() as u64;
+
+    Ok(ulid(timestamp, random_bits(ULID_RANDOM_BITS, rng)).into())
+}
+
+/// Generate `count` ULIDs one at a time, each strictly greater than the one before it, even within the same millisecond.
+pub fn generate_monotonic_ulids<R: Rng>(count: usize, mut rng: R) -> impl Iterator<Item = Result<Secret>> {
+    let mut monotonic = Monotonic::new(ULID_RANDOM_BITS);
+
+    (0..count).map(move |_| monotonic.next(&mut rng).map(|(timestamp, random)| ulid(timestamp, random).into()))
+}
+
+/// Generate a NanoID of `size` characters, each chosen uniformly from the alphabet.
+pub fn generate_nanoid<R: Rng>(alphabet: &[char], size: usize, rng: &mut R) -> Result<Secret> {
+    let mut seen = HashSet::<char>::with_capacity(alphabet.len());
+
+    if let Some(duplicate) = alphabet.iter().find(|character| !seen.insert(**character)) {
+        return Err(Error::InvalidArgument(format!("The alphabet contains {duplicate:?} more than once, which would bias the output")));
+    }
+
+    let distribution = Slice::new(alphabet)
+        .map_err(|_| Error::EmptyAlphabet("The alphabet contains no characters".into()))?;
+
+    let nanoid = rng
+        .sample_iter(distribution)
+        .take(size)
+        .collect::<Secret>();
+
+    Ok(nanoid)
+}
+
+/// Generate a K-Sortable Unique Identifier from a timestamp in seconds and 128 random bits.
+pub fn generate_ksuid<R: Rng>(rng: &mut R) -> Result<Secret> {
+    let timestamp = unix_time()?.as_secs().saturating_sub(KSUID_EPOCH) as u32;
+    let bytes = [timestamp.to_be_bytes().as_slice(), &generate_bytes(KSUID_RANDOM_BITS as usize / 8, rng)?].concat();
+
+    Ok(encode_fixed_radix(&bytes, BASE62, KSUID_LENGTH))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn generates_uuid_v4() {
+        let bytes = generate_uuid(UuidVersion::V4, &mut thread_rng()).unwrap();
+        let uuid = from_utf8(&bytes).unwrap();
+
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!("89ab".contains(&uuid[19..20]), "expected an RFC 9562 variant, but got {uuid}");
+    }
+
+    #[test]
+    fn generates_uuid_v7_with_current_timestamp() {
+        let before = unix_time().unwrap().as_millis() as u64;
+        let bytes = generate_uuid(UuidVersion::V7, &mut thread_rng()).unwrap();
+        let uuid = from_utf8(&bytes).unwrap();
+        let timestamp = u64::from_str_radix(&uuid[..13].replace('-', ""), 16).unwrap();
+
+        assert_eq!(&uuid[14..15], "7");
+        assert!("89ab".contains(&uuid[19..20]), "expected an RFC 9562 variant, but got {uuid}");
+        assert!(timestamp >= before && timestamp <= before + 1000);
+    }
+
+    #[test]
+    fn generates_monotonic_uuids() {
+        let uuids = generate_monotonic_uuids(1000, thread_rng()).collect::<Result<Vec<_>>>().unwrap();
+
+        assert!(uuids.windows(2).all(|pair| pair[0][..] < pair[1][..]));
+    }
+
+    #[test]
+    fn generates_monotonic_ulids() {
+        let ulids = generate_monotonic_ulids(1000, thread_rng()).collect::<Result<Vec<_>>>().unwrap();
+
+        assert!(ulids.iter().all(|ulid| ulid.len() == 26));
+        assert!(ulids.windows(2).all(|pair| pair[0][..] < pair[1][..]));
+    }
+
+    #[test]
+    fn generates_nanoid_from_alphabet() {
+        let alphabet = ['a', 'b', 'c'];
+        let bytes = generate_nanoid(&alphabet, 32, &mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), 32);
+        assert!(bytes.iter().all(|byte| b"abc".contains(byte)));
+    }
+
+    #[test]
+    fn rejects_invalid_nanoid_alphabets() {
+        assert!(matches!(generate_nanoid(&[], 21, &mut thread_rng()), Err(Error::EmptyAlphabet(_))));
+        assert!(matches!(generate_nanoid(&['a', 'b', 'a'], 21, &mut thread_rng()), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn generates_ksuid() {
+        let bytes = generate_ksuid(&mut thread_rng()).unwrap();
+
+        assert_eq!(bytes.len(), KSUID_LENGTH);
+        assert!(bytes.iter().all(|byte| BASE62.contains(byte)));
+    }
+}
@@ -0,0 +1,341 @@
+use std::fmt;
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use bech32::{ToBase32, Variant};
+use clap::ValueEnum;
+use ed25519_dalek::SigningKey;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::{EncodePrivateKey, EncodePublicKey};
+use p256::SecretKey;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use ssh_key::private::{EcdsaKeypair, Ed25519Keypair, KeypairData};
+use ssh_key::{HashAlg, PrivateKey};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use super::binary::generate_bytes;
+use crate::encoding::wrap_lines;
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// The DER encoding of a PKCS#8 private key, up to the key itself, for Ed25519 (RFC 8410).
+const ED25519_PRIVATE_PREFIX: [u8; 16] = [0x30, 0x2E, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20];
+
+/// The DER encoding of a SubjectPublicKeyInfo structure, up to the key itself, for Ed25519 (RFC 8410).
+const ED25519_PUBLIC_PREFIX: [u8; 12] = [0x30, 0x2A, 0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+/// The DER encoding of a PKCS#8 private key, up to the key itself, for X25519 (RFC 8410).
+const X25519_PRIVATE_PREFIX: [u8; 16] = [0x30, 0x2E, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x6E, 0x04, 0x22, 0x04, 0x20];
+
+/// The DER encoding of a SubjectPublicKeyInfo structure, up to the key itself, for X25519 (RFC 8410).
+const X25519_PUBLIC_PREFIX: [u8; 12] = [0x30, 0x2A, 0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x6E, 0x03, 0x21, 0x00];
+
+/// The algorithms that key pairs can be generated for.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyType {
+    /// Edwards-curve signing keys, as used by OpenSSH, minisign and JOSE
+    Ed25519,
+    /// Montgomery-curve key agreement keys, as used by WireGuard and age
+    X25519,
+    /// NIST P-256 ECDSA keys
+    P256
+}
+
+impl KeyType {
+    /// The number of bits of entropy in a private key, which is generated from a 32-byte seed.
+    pub fn entropy(self) -> u32 {
+        match self {
+            // Clamping the scalar fixes five bits of the seed.
+            KeyType::X25519 => 251,
+            KeyType::Ed25519 | KeyType::P256 => 256
+        }
+    }
+}
+
+/// The formats that key pairs can be written in.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyFormat {
+    /// An OpenSSH private key, with the public key in authorized_keys format
+    Openssh,
+    /// A PKCS#8 private key and a SubjectPublicKeyInfo public key, both PEM-encoded
+    Pem,
+    /// A JSON Web Key, with its RFC 7638 thumbprint as the fingerprint
+    Jwk,
+    /// A Base64-encoded WireGuard private and public key
+    Wireguard,
+    /// An age identity and recipient
+    Age
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyType::Ed25519 => write!(formatter, "Ed25519"),
+            KeyType::X25519 => write!(formatter, "X25519"),
+            KeyType::P256 => write!(formatter, "P-256")
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyFormat::Openssh => write!(formatter, "OpenSSH"),
+            KeyFormat::Pem => write!(formatter, "PEM"),
+            KeyFormat::Jwk => write!(formatter, "JWK"),
+            KeyFormat::Wireguard => write!(formatter, "WireGuard"),
+            KeyFormat::Age => write!(formatter, "age")
+        }
+    }
+}
+
+/// A newly-generated key pair, encoded in one of the supported formats.
+pub struct Keypair {
+    /// The private key, which is the secret output.
+    pub private: Secret,
+    /// The public key, which is safe to share.
+    pub public: String,
+    /// A short digest that identifies the public key.
+    pub fingerprint: String
+}
+
+/// Generate a key pair of the given type, and encode it in the given format.
+pub fn generate_keypair<R: Rng>(kind: KeyType, format: KeyFormat, rng: &mut R) -> Result<Keypair> {
+    let supported = match format {
+        KeyFormat::Openssh => kind != KeyType::X25519,
+        KeyFormat::Wireguard | KeyFormat::Age => kind == KeyType::X25519,
+        KeyFormat::Pem | KeyFormat::Jwk => true
+    };
+
+    if !supported { return Err(Error::InvalidArgument(format!("{kind} keys cannot be written in the {format} format"))); }
+
+    match kind {
+        KeyType::Ed25519 => encode_ed25519(&*generate_seed(rng)?, format),
+        KeyType::X25519 => encode_x25519(&*generate_seed(rng)?, format),
+        KeyType::P256 => {
+            // Roughly one in 2^32 seeds is not a valid scalar, so try again in that case.
+            let secret = loop {
+                if let Ok(secret) = SecretKey::from_slice(generate_seed(rng)?.as_slice()) { break secret; }
+            };
+
+            encode_p256(&secret, format)
+        }
+    }
+}
+
+fn generate_seed<R: Rng>(rng: &mut R) -> Result<Zeroizing<[u8; 32]>> {
+    let bytes = generate_bytes(32, rng)?;
+    let mut seed = Zeroizing::new([0_u8; 32]);
+
+    seed.copy_from_slice(&bytes);
+
+    Ok(seed)
+}
+
+fn encode_ed25519(seed: &[u8; 32], format: KeyFormat) -> Result<Keypair> {
+    let public = SigningKey::from_bytes(seed).verifying_key().to_bytes();
+
+    match format {
+        KeyFormat::Openssh => encode_openssh(Ed25519Keypair::from_seed(seed).into()),
+        KeyFormat::Pem => Ok(encode_pem(&Zeroizing::new([&ED25519_PRIVATE_PREFIX, seed.as_slice()].concat()), &[&ED25519_PUBLIC_PREFIX, public.as_slice()].concat())),
+        KeyFormat::Jwk => Ok(encode_okp_jwk("Ed25519", seed, &public)),
+        KeyFormat::Wireguard | KeyFormat::Age => unreachable!()
+    }
+}
+
+fn encode_x25519(seed: &[u8; 32], format: KeyFormat) -> Result<Keypair> {
+    // Clamp the scalar like `wg genkey` does, which leaves the public key unchanged but makes every format hold the same bytes.
+    let mut private = Zeroizing::new(*seed);
+
+    private[0] &= 248;
+    private[31] &= 127;
+    private[31] |= 64;
+
+    let public = PublicKey::from(&StaticSecret::from(*private)).to_bytes();
+    let fingerprint = format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(public)));
+
+    match format {
+        KeyFormat::Pem => Ok(encode_pem(&Zeroizing::new([&X25519_PRIVATE_PREFIX, private.as_slice()].concat()), &[&X25519_PUBLIC_PREFIX, public.as_slice()].concat())),
+        KeyFormat::Jwk => Ok(encode_okp_jwk("X25519", private.as_slice(), &public)),
+        KeyFormat::Wireguard => Ok(Keypair {
+            private: Secret::from(STANDARD.encode(private)),
+            public: STANDARD.encode(public),
+            fingerprint
+        }),
+        KeyFormat::Age => {
+            let identity = bech32::encode("age-secret-key-", private.to_base32(), Variant::Bech32)
+                .map(Zeroizing::new)
+                .map_err(|error| Error::Internal(error.to_string()))?;
+            let recipient = bech32::encode("age", public.to_base32(), Variant::Bech32)
+                .map_err(|error| Error::Internal(error.to_string()))?;
+
+            Ok(Keypair { private: Secret::from(identity.to_uppercase()), public: recipient, fingerprint })
+        }
+        KeyFormat::Openssh => unreachable!()
+    }
+}
+
+fn encode_p256(secret: &SecretKey, format: KeyFormat) -> Result<Keypair> {
+    let public = secret.public_key();
+
+    match format {
+        KeyFormat::Openssh => {
+            let keypair = EcdsaKeypair::NistP256 { public: public.to_encoded_point(false), private: secret.clone().into() };
+
+            encode_openssh(keypair.into())
+        }
+        KeyFormat::Pem => {
+            let private = secret.to_pkcs8_der().map_err(|error| Error::Internal(error.to_string()))?;
+            let public = public.to_public_key_der().map_err(|error| Error::Internal(error.to_string()))?;
+
+            Ok(encode_pem(private.as_bytes(), public.as_bytes()))
+        }
+        KeyFormat::Jwk => {
+            let point = public.to_encoded_point(false);
+            // An uncompressed point always has both coordinates.
+            let x = URL_SAFE_NO_PAD.encode(point.x().unwrap());
+            let y = URL_SAFE_NO_PAD.encode(point.y().unwrap());
+            let d = Zeroizing::new(URL_SAFE_NO_PAD.encode(secret.to_bytes()));
+            let public = format!(r#"{{"crv":"P-256","kty":"EC","x":"{x}","y":"{y}"}}"#);
+
+            Ok(Keypair {
+                private: Secret::from(format!(r#"{{"crv":"P-256","d":"{}","kty":"EC","x":"{x}","y":"{y}"}}"#, d.as_str())),
+                fingerprint: URL_SAFE_NO_PAD.encode(Sha256::digest(&public)),
+                public
+            })
+        }
+        KeyFormat::Wireguard | KeyFormat::Age => unreachable!()
+    }
+}
+
+/// Encode a key pair in the OpenSSH format, with the same fingerprint that `ssh-keygen -l` prints.
+fn encode_openssh(keypair: KeypairData) -> Result<Keypair> {
+    let convert = |error: ssh_key::Error| Error::Internal(error.to_string());
+    let private = PrivateKey::new(keypair, "").map_err(convert)?;
+    let encoded = private.to_openssh(ssh_key::LineEnding::LF).map_err(convert)?;
+    let public = private.public_key().to_openssh().map_err(convert)?;
+
+    Ok(Keypair {
+        private: Secret::from(encoded.as_bytes()),
+        public,
+        fingerprint: private.fingerprint(HashAlg::Sha256).to_string()
+    })
+}
+
+/// Encode DER-encoded PKCS#8 and SubjectPublicKeyInfo structures as PEM, fingerprinting the latter like `openssl pkey -pubout -outform DER | sha256sum` would.
+fn encode_pem(private: &[u8], public: &[u8]) -> Keypair {
+    let armor = |label: &str, der: &[u8]| {
+        let encoded = Zeroizing::new(STANDARD.encode(der));
+        let mut armored = Secret::default();
+
+        armored.extend_from_slice(format!("-----BEGIN {label}-----\n").as_bytes());
+        armored.extend_from_slice(&wrap_lines(encoded.as_bytes(), 64));
+        armored.extend_from_slice(format!("\n-----END {label}-----\n").as_bytes());
+
+        armored
+    };
+
+    Keypair {
+        private: armor("PRIVATE KEY", private),
+        public: String::from_utf8_lossy(&armor("PUBLIC KEY", public)).into_owned(),
+        fingerprint: format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(public)))
+    }
+}
+
+/// Encode an octet key pair as a JSON Web Key (RFC 8037), with members in the lexicographic order that RFC 7638 thumbprints require.
+fn encode_okp_jwk(curve: &str, private: &[u8], public: &[u8]) -> Keypair {
+    let x = URL_SAFE_NO_PAD.encode(public);
+    let d = Zeroizing::new(URL_SAFE_NO_PAD.encode(private));
+    let public = format!(r#"{{"crv":"{curve}","kty":"OKP","x":"{x}"}}"#);
+
+    Keypair {
+        private: Secret::from(format!(r#"{{"crv":"{curve}","d":"{}","kty":"OKP","x":"{x}"}}"#, d.as_str())),
+        fingerprint: URL_SAFE_NO_PAD.encode(Sha256::digest(&public)),
+        public
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+
+    use p256::pkcs8::{DecodePrivateKey, LineEnding};
+    use rand::thread_rng;
+
+    use super::*;
+
+    /// A fixed Ed25519 seed, whose public key was computed independently with OpenSSL.
+    const ED25519_SEED: [u8; 32] = [
+        0x9D, 0x61, 0xB1, 0x9D, 0xEF, 0xFD, 0x5A, 0x60, 0xBA, 0x84, 0x4A, 0xF4, 0x92, 0xEC, 0x2C, 0xC4,
+        0x44, 0x49, 0xC5, 0x69, 0x7B, 0x87, 0x69, 0x19, 0x70, 0x3B, 0xAC, 0x03, 0x1C, 0xAE, 0x7F, 0x60
+    ];
+
+    /// A fixed X25519 private key, whose public key was computed independently with OpenSSL.
+    const X25519_SEED: [u8; 32] = [
+        0x77, 0x07, 0x6D, 0x0A, 0x73, 0x18, 0xA5, 0x7D, 0x3C, 0x16, 0xC1, 0x72, 0x51, 0xB2, 0x66, 0x45,
+        0xDF, 0x4C, 0x2F, 0x87, 0xEB, 0xE0, 0x87, 0x5A, 0x0D, 0xC5, 0x78, 0x2A, 0x2C, 0x6A, 0xB1, 0x2A
+    ];
+
+    #[test]
+    fn encodes_ed25519_jwk() {
+        let keypair = encode_ed25519(&ED25519_SEED, KeyFormat::Jwk).unwrap();
+
+        assert_eq!(keypair.public, r#"{"crv":"Ed25519","kty":"OKP","x":"isk9HqoFylCUAEVqmwOJfltTTVwBrG2nTNB26lzfDqQ"}"#);
+        assert_eq!(from_utf8(&keypair.private).unwrap(), r#"{"crv":"Ed25519","d":"nWGxne_9WmC6hEr0kuwsxERJxWl7h2kZcDusAxyuf2A","kty":"OKP","x":"isk9HqoFylCUAEVqmwOJfltTTVwBrG2nTNB26lzfDqQ"}"#);
+    }
+
+    #[test]
+    fn encodes_ed25519_openssh_keys_that_parse() {
+        let keypair = encode_ed25519(&ED25519_SEED, KeyFormat::Openssh).unwrap();
+        let private = PrivateKey::from_openssh(&keypair.private).unwrap();
+
+        assert!(keypair.public.starts_with("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5"));
+        assert_eq!(private.public_key().to_openssh().unwrap(), keypair.public);
+        assert!(keypair.fingerprint.starts_with("SHA256:"));
+    }
+
+    #[test]
+    fn encodes_x25519_wireguard_keys() {
+        let keypair = encode_x25519(&X25519_SEED, KeyFormat::Wireguard).unwrap();
+        let public = STANDARD.decode(&keypair.public).unwrap();
+
+        assert_eq!(hex::encode(public), "ee46988038b2d75877dd43eb46524e0f478ab2d1a750bca70729b154ae968830");
+        assert_eq!(STANDARD.decode(&keypair.private).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn encodes_x25519_age_identity() {
+        let keypair = encode_x25519(&X25519_SEED, KeyFormat::Age).unwrap();
+        let private = from_utf8(&keypair.private).unwrap();
+
+        assert!(private.starts_with("AGE-SECRET-KEY-1"));
+        assert!(keypair.public.starts_with("age1"));
+        assert_eq!(keypair.public.len(), 62);
+    }
+
+    #[test]
+    fn encodes_p256_pem_keys_that_parse() {
+        let keypair = generate_keypair(KeyType::P256, KeyFormat::Pem, &mut thread_rng()).unwrap();
+        let private = from_utf8(&keypair.private).unwrap();
+        let secret = SecretKey::from_pkcs8_pem(private).unwrap();
+
+        assert_eq!(secret.public_key().to_public_key_pem(LineEnding::LF).unwrap(), keypair.public);
+    }
+
+    #[test]
+    fn generates_distinct_keypairs() {
+        let first = generate_keypair(KeyType::Ed25519, KeyFormat::Pem, &mut thread_rng()).unwrap();
+        let second = generate_keypair(KeyType::Ed25519, KeyFormat::Pem, &mut thread_rng()).unwrap();
+
+        assert_ne!(first.private, second.private)
+    }
+
+    #[test]
+    fn rejects_incompatible_formats() {
+        for (kind, format) in [(KeyType::X25519, KeyFormat::Openssh), (KeyType::Ed25519, KeyFormat::Wireguard), (KeyType::P256, KeyFormat::Age)] {
+            assert!(matches!(generate_keypair(kind, format, &mut thread_rng()), Err(Error::InvalidArgument(_))));
+        }
+    }
+}
@@ -4,10 +4,22 @@ pub mod passphrase;
 pub mod username;
 pub mod digits;
 pub mod number;
+pub mod games;
+pub mod keypair;
+pub mod identifier;
+pub mod otp;
+pub mod token;
+pub mod network;
 
-pub use binary::{generate_bytes, generate_hex, generate_base64};
+pub use binary::{Base64Alphabet, generate_bytes, generate_hex, generate_base64, generate_base32, generate_crockford, generate_base58, generate_z85, generate_base85};
 pub use password::generate_password;
 pub use passphrase::generate_passphrase;
-pub use username::{generate_simple_username, generate_complex_username};
-pub use digits::generate_digits;
-pub use number::generate_number;
+pub use username::{generate_simple_username, generate_complex_username, simple_username_entropy, complex_username_entropy};
+pub use digits::{generate_checked_digits, generate_secure_pin};
+pub use number::{generate_number, generate_unique_numbers, generate_permutation, format_number, parse_number, range_size};
+pub use games::{generate_dice, generate_coin, generate_cards, shoe_size, DiceNotation};
+pub use keypair::{generate_keypair, KeyFormat, KeyType};
+pub use identifier::{generate_uuid, generate_monotonic_uuids, generate_ulid, generate_monotonic_ulids, generate_nanoid, generate_ksuid, UuidVersion, KSUID_RANDOM_BITS, NANOID_ALPHABET, ULID_RANDOM_BITS};
+pub use otp::{generate_otp, OtpAlgorithm, OtpKind};
+pub use token::{generate_token, verify_token, TokenChecksum};
+pub use network::{generate_mac, generate_ula, generate_ip, Cidr, MacStyle, MAC_RANDOM_BITS, ULA_RANDOM_BITS};
@@ -0,0 +1,220 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use rand::Rng;
+
+use super::binary::generate_bytes;
+use crate::error::Result;
+use crate::secret::Secret;
+
+/// The number of random bits in a MAC address, after the unicast and locally-administered bits.
+pub const MAC_RANDOM_BITS: u32 = 46;
+
+/// The number of random bits in a unique local IPv6 prefix, which is its global ID.
+pub const ULA_RANDOM_BITS: u32 = 40;
+
+/// The ways in which the octets of a MAC address can be grouped.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MacStyle {
+    /// Six groups of two digits separated by colons, such as 02:00:5e:10:00:01
+    Colon,
+    /// Six groups of two digits separated by dashes, such as 02-00-5e-10-00-01
+    Dash,
+    /// Three groups of four digits separated by dots, such as 0200.5e10.0001
+    Dot
+}
+
+/// A block of IPv4 or IPv6 addresses in CIDR notation, such as `10.0.0.0/8` or `fd00::/64`.
+#[derive(Clone)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix: u32
+}
+
+impl Cidr {
+    /// The number of bits in an address of this family.
+    fn bits(&self) -> u32 {
+        if self.address.is_ipv4() { 32 } else { 128 }
+    }
+
+    /// The number of bits of entropy in an address chosen from the block, leaving out the addresses that are never chosen.
+    pub fn entropy(&self) -> f64 {
+        let host_bits = self.bits() - self.prefix;
+        let reserved = match (host_bits >= 2, self.address.is_ipv4()) {
+            (false, _) => 0.0,
+            (true, true) => 2.0,
+            (true, false) => 1.0
+        };
+
+        host_bits as f64 + (1.0 - reserved / 2_f64.powi(host_bits as i32)).log2()
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}/{}", self.address, self.prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("{value} is not a valid block of addresses in CIDR notation, such as 10.0.0.0/8");
+        let (address, prefix) = value.trim().split_once('/').ok_or_else(invalid)?;
+        let address = address.parse::<IpAddr>().map_err(|_| invalid())?;
+        let prefix = prefix.parse::<u32>().map_err(|_| invalid())?;
+        let cidr = Cidr { address, prefix };
+
+        if prefix > cidr.bits() { return Err(invalid()); }
+
+        Ok(cidr)
+    }
+}
+
+/// Generate a random unicast MAC address, with the locally-administered bit set so that it can never clash with a manufacturer's address.
+pub fn generate_mac<R: Rng>(style: MacStyle, uppercase: bool, rng: &mut R) -> Result<Secret> {
+    let mut bytes = generate_bytes(6, rng)?;
+
+    bytes[0] = (bytes[0] & 0b1111_1100) | 0b0000_0010;
+
+    let digits = if uppercase { hex::encode_upper(&bytes) } else { hex::encode(&bytes) };
+    let (size, separator) = match style {
+        MacStyle::Colon => (2, ":"),
+        MacStyle::Dash => (2, "-"),
+        MacStyle::Dot => (4, ".")
+    };
+
+    let address = digits
+        .as_bytes()
+        .chunks(size)
+        .map(|group| String::from_utf8_lossy(group))
+        .collect::<Vec<_>>()
+        .join(separator);
+
+    Ok(address.into())
+}
+
+/// Generate an RFC 4193 unique local IPv6 /48 prefix, which is `fd` followed by a random 40-bit global ID.
+pub fn generate_ula<R: Rng>(rng: &mut R) -> Result<Secret> {
+    let mut bytes = [0_u8; 16];
+
+    bytes[0] = 0xFD;
+    bytes[1..6].copy_from_slice(&generate_bytes(5, rng)?);
+
+    Ok(format!("{}/48", Ipv6Addr::from(bytes)).into())
+}
+
+/// Generate a random address inside a block of addresses.
+///
+/// When there are at least four addresses to choose from, the network address is never chosen, and neither is the broadcast address of an IPv4 block.
+pub fn generate_ip<R: Rng>(cidr: &Cidr, rng: &mut R) -> Result<Secret> {
+    let ipv4 = cidr.address.is_ipv4();
+    let network = match cidr.address {
+        IpAddr::V4(address) => u32::from(address) as u128,
+        IpAddr::V6(address) => u128::from(address)
+    };
+    let host_bits = cidr.bits() - cidr.prefix;
+    let host_mask = if host_bits == 0 { 0 } else { u128::MAX >> (128 - host_bits) };
+    let network = network & !host_mask;
+
+    let host = loop {
+        let bytes = generate_bytes(16, rng)?;
+        let host = u128::from_be_bytes(bytes[..].try_into().unwrap()) & host_mask;
+        let reserved = host_bits >= 2 && (host == 0 || (ipv4 && host == host_mask));
+
+        if !reserved { break host; }
+    };
+
+    let address = if ipv4 { IpAddr::V4(Ipv4Addr::from((network | host) as u32)) }
+    else { IpAddr::V6(Ipv6Addr::from(network | host)) };
+
+    Ok(address.to_string().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn generates_locally_administered_unicast_mac() {
+        let bytes = generate_mac(MacStyle::Colon, false, &mut thread_rng()).unwrap();
+        let address = from_utf8(&bytes).unwrap();
+        let first = u8::from_str_radix(&address[..2], 16).unwrap();
+
+        assert_eq!(address.len(), 17);
+        assert_eq!(address.matches(':').count(), 5);
+        assert_eq!(first & 0b11, 0b10);
+    }
+
+    #[test]
+    fn formats_mac_with_dots() {
+        let bytes = generate_mac(MacStyle::Dot, true, &mut thread_rng()).unwrap();
+        let address = from_utf8(&bytes).unwrap();
+
+        assert_eq!(address.len(), 14);
+        assert_eq!(address.matches('.').count(), 2);
+        assert!(!address.chars().any(|character| character.is_ascii_lowercase()));
+    }
+
+    #[test]
+    fn generates_ula_prefix() {
+        let bytes = generate_ula(&mut thread_rng()).unwrap();
+        let prefix = from_utf8(&bytes).unwrap();
+
+        assert!(prefix.starts_with("fd"));
+        assert!(prefix.ends_with("::/48"));
+        assert!(prefix.trim_end_matches("/48").parse::<Ipv6Addr>().is_ok());
+    }
+
+    #[test]
+    fn generates_ipv4_inside_cidr() {
+        let cidr = "192.168.4.0/30".parse::<Cidr>().unwrap();
+
+        for _ in 0..100 {
+            let bytes = generate_ip(&cidr, &mut thread_rng()).unwrap();
+            let address = from_utf8(&bytes).unwrap();
+
+            assert!(address == "192.168.4.1" || address == "192.168.4.2", "expected a host address, but got {address}");
+        }
+    }
+
+    #[test]
+    fn generates_ipv6_inside_cidr() {
+        let cidr = "2001:db8:1:2::/64".parse::<Cidr>().unwrap();
+        let bytes = generate_ip(&cidr, &mut thread_rng()).unwrap();
+        let address = from_utf8(&bytes).unwrap().parse::<Ipv6Addr>().unwrap();
+
+        assert_eq!(address.segments()[..4], [0x2001, 0x0DB8, 0x0001, 0x0002]);
+    }
+
+    #[test]
+    fn generates_single_address() {
+        let cidr = "10.1.2.3/32".parse::<Cidr>().unwrap();
+
+        assert_eq!(generate_ip(&cidr, &mut thread_rng()).unwrap(), b"10.1.2.3");
+    }
+
+    #[test]
+    fn rejects_invalid_cidr() {
+        for value in ["", "10.0.0.0", "10.0.0.0/33", "fd00::/129", "10.0.0/8", "example/8"] {
+            assert!(value.parse::<Cidr>().is_err(), "expected {value} to be rejected");
+        }
+    }
+
+    #[test]
+    fn calculates_entropy_without_reserved_addresses() {
+        let ipv4 = "10.0.0.0/30".parse::<Cidr>().unwrap();
+        let ipv6 = "fd00::/127".parse::<Cidr>().unwrap();
+
+        assert!((ipv4.entropy() - 1.0).abs() < 1e-9);
+        assert!((ipv6.entropy() - 1.0).abs() < 1e-9);
+        assert_eq!(ipv4.to_string(), "10.0.0.0/30");
+    }
+}
@@ -1,34 +1,241 @@
-use std::process::exit;
+use std::collections::HashSet;
 
-use log::error;
-use rand::{thread_rng, Rng};
+use num_bigint::{BigInt, RandBigInt};
+use num_traits::{Signed, Zero};
+use rand::{seq::SliceRandom, Rng};
 
-pub fn generate_number(minimum: usize, maximum: usize) -> Vec<u8> {
-    if minimum >= maximum {
-        error!("{} is greater than or equal to {}", minimum, maximum);
-        exit(-1);
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// Parse a decimal string into an integer scaled by `10^precision`.
+pub fn parse_number(value: &str, precision: u32) -> Result<BigInt> {
+    let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let width = precision as usize;
+
+    if fraction.len() > width { return Err(Error::InvalidArgument(format!("{value} has more than {precision} decimal places"))); }
+
+    if !fraction.chars().all(|character| character.is_ascii_digit()) { return Err(Error::InvalidArgument(format!("{value} is not a valid number"))); }
+
+    let number = format!("{integer}{fraction:0<width$}")
+        .parse::<BigInt>()
+        .map_err(|_| Error::InvalidArgument(format!("{value} is not a valid number")))?;
+
+    Ok(number)
+}
+
+/// Format an integer scaled by `10^precision` as a string in the given radix.
+pub fn format_number(value: &BigInt, precision: u32, radix: u32) -> String {
+    if precision == 0 { return value.to_str_radix(radix); }
+
+    let width = precision as usize + 1;
+    let digits = format!("{:0>width$}", value.magnitude().to_str_radix(10));
+    let (integer, fraction) = digits.split_at(digits.len() - precision as usize);
+    let sign = if value.is_negative() { "-" } else { "" };
+
+    format!("{sign}{integer}.{fraction}")
+}
+
+/// Count the numbers between `minimum` and `maximum` inclusive that are a whole multiple of `step` above `minimum`.
+pub fn range_size(minimum: &BigInt, maximum: &BigInt, step: &BigInt, precision: u32) -> Result<BigInt> {
+    if minimum > maximum {
+        let message = format!("{} is greater than {}", format_number(minimum, precision, 10), format_number(maximum, precision, 10));
+
+        return Err(Error::InvalidRange(message));
+    }
+
+    if !step.is_positive() { return Err(Error::InvalidRange("The step must be greater than zero".into())); }
+
+    Ok((maximum - minimum) / step + 1)
+}
+
+/// Generate a random number between `minimum` and `maximum` inclusive.
+///
+/// The bounds and the step are integers scaled by `10^precision`,
+/// so that fixed-precision decimals can be generated without any loss of accuracy.
+/// Only numbers that can be reached by adding a whole multiple of `step` to `minimum` are generated.
+pub fn generate_number<R: Rng>(minimum: &BigInt, maximum: &BigInt, step: &BigInt, precision: u32, radix: u32, rng: &mut R) -> Result<Secret> {
+    let size = range_size(minimum, maximum, step, precision)?;
+    let offset = rng.gen_bigint_range(&BigInt::zero(), &size);
+    let value = minimum + offset * step;
+
+    Ok(format_number(&value, precision, radix).into())
+}
+
+/// Generate `count` distinct random numbers between `minimum` and `maximum` inclusive, in random order.
+///
+/// Numbers are sampled without replacement using Floyd's algorithm,
+/// so that the memory required is proportional to `count` rather than to the size of the range,
+/// and each one is only formatted when the iterator reaches it.
+pub fn generate_unique_numbers<R: Rng>(minimum: &BigInt, maximum: &BigInt, step: &BigInt, precision: u32, radix: u32, count: usize, rng: &mut R) -> Result<impl Iterator<Item = Secret>> {
+    let size = range_size(minimum, maximum, step, precision)?;
+
+    if BigInt::from(count) > size { return Err(Error::PolicyInfeasible(format!("Cannot choose {count} unique numbers from a range of only {size} numbers"))); }
+
+    let mut chosen = HashSet::<BigInt>::with_capacity(count);
+    let mut offsets = Vec::<BigInt>::with_capacity(count);
+
+    for position in 0..count {
+        let index = &size - count + position;
+        let candidate = rng.gen_bigint_range(&BigInt::zero(), &(&index + 1));
+        let offset = if chosen.contains(&candidate) { index } else { candidate };
+
+        chosen.insert(offset.clone());
+        offsets.push(offset);
     }
 
-    let range = minimum..=maximum;
+    // Floyd's algorithm chooses a uniform set, but not a uniform order.
+    offsets.shuffle(rng);
+
+    let (minimum, step) = (minimum.clone(), step.clone());
 
-    thread_rng()
-        .gen_range(range)
-        .to_string()
-        .into_bytes()
+    Ok(offsets.into_iter().map(move |offset| format_number(&(&minimum + offset * &step), precision, radix).into()))
+}
+
+/// Generate a random permutation of the `length` consecutive integers that begin with `start`.
+///
+/// Only the offsets are shuffled, and each integer is formatted when the iterator reaches it.
+pub fn generate_permutation<R: Rng>(start: &BigInt, length: usize, rng: &mut R) -> Result<impl Iterator<Item = Secret>> {
+    let mut offsets = (0..length).collect::<Vec<_>>();
+
+    offsets.shuffle(rng);
+
+    let start = start.clone();
+
+    Ok(offsets.into_iter().map(move |offset| (&start + offset).to_string().into()))
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::from_utf8;
 
+    use rand::thread_rng;
+
     use super::*;
 
+    fn generate(minimum: &str, maximum: &str, step: &str, precision: u32, radix: u32) -> String {
+        let minimum = parse_number(minimum, precision).unwrap();
+        let maximum = parse_number(maximum, precision).unwrap();
+        let step = parse_number(step, precision).unwrap();
+        let bytes = generate_number(&minimum, &maximum, &step, precision, radix, &mut thread_rng()).unwrap();
+
+        from_utf8(&bytes).unwrap().to_owned()
+    }
+
     #[test]
     fn generates_number() {
-        let bytes = generate_number(0, 1024);
-        let string = from_utf8(&bytes).unwrap();
-        let number = u64::from_str_radix(string, 10).unwrap();
+        let string = generate("0", "1024", "1", 0, 10);
+        let number = string.parse::<u64>().unwrap();
+
+        assert!(number <= 1024)
+    }
+
+    #[test]
+    fn generates_negative_number() {
+        let string = generate("-1024", "-512", "1", 0, 10);
+        let number = string.parse::<i64>().unwrap();
+
+        assert!((-1024..=-512).contains(&number))
+    }
+
+    #[test]
+    fn generates_number_when_bounds_are_equal() {
+        assert_eq!(generate("42", "42", "1", 0, 10), "42")
+    }
+
+    #[test]
+    fn generates_number_beyond_native_integers() {
+        let string = generate("340282366920938463463374607431768211456", "340282366920938463463374607431768211457", "1", 0, 10);
+
+        assert!(string.parse::<BigInt>().unwrap() > BigInt::from(u128::MAX))
+    }
+
+    #[test]
+    fn generates_decimal_number() {
+        let string = generate("-0.5", "0.5", "0.001", 3, 10);
+        let (_, fraction) = string.split_once('.').unwrap();
+        let number = string.parse::<f64>().unwrap();
+
+        assert_eq!(fraction.len(), 3);
+        assert!((-0.5..=0.5).contains(&number))
+    }
+
+    #[test]
+    fn generates_multiple_of_step() {
+        let string = generate("0", "1000", "7", 0, 10);
+        let number = string.parse::<u64>().unwrap();
+
+        assert_eq!(number % 7, 0)
+    }
+
+    #[test]
+    fn generates_number_in_radix() {
+        let string = generate("0", "65535", "1", 0, 16);
+
+        assert!(u64::from_str_radix(&string, 16).unwrap() <= 65535)
+    }
+
+    #[test]
+    fn generates_unique_numbers() {
+        let numbers = generate_unique_numbers(&BigInt::from(1), &BigInt::from(100), &BigInt::from(1), 0, 10, 100, &mut thread_rng()).unwrap().collect::<Vec<_>>();
+        let unique = numbers.iter().collect::<HashSet<_>>();
+
+        assert_eq!(numbers.len(), 100);
+        assert_eq!(unique.len(), 100);
+    }
+
+    #[test]
+    fn generates_unique_multiples_of_step() {
+        let numbers = generate_unique_numbers(&BigInt::from(0), &BigInt::from(1000), &BigInt::from(10), 0, 10, 50, &mut thread_rng()).unwrap();
+
+        for number in numbers {
+            let number = from_utf8(&number).unwrap().parse::<u64>().unwrap();
 
-        assert!(number < 1024)
+            assert_eq!(number % 10, 0)
+        }
+    }
+
+    #[test]
+    fn generates_permutation() {
+        let mut values = generate_permutation(&BigInt::from(1), 1000, &mut thread_rng())
+            .unwrap()
+            .map(|value| from_utf8(&value).unwrap().parse::<u64>().unwrap())
+            .collect::<Vec<_>>();
+
+        values.sort();
+
+        assert_eq!(values, (1..=1000).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn rejects_inverted_range() {
+        let result = generate_number(&BigInt::from(2), &BigInt::from(1), &BigInt::from(1), 0, 10, &mut thread_rng());
+
+        assert!(matches!(result, Err(Error::InvalidRange(_))))
+    }
+
+    #[test]
+    fn rejects_too_many_unique_numbers() {
+        let result = generate_unique_numbers(&BigInt::from(1), &BigInt::from(5), &BigInt::from(1), 0, 10, 6, &mut thread_rng());
+
+        assert!(matches!(result, Err(Error::PolicyInfeasible(_))))
+    }
+
+    #[test]
+    fn parses_scaled_number() {
+        assert_eq!(parse_number("1.5", 2).unwrap(), BigInt::from(150));
+        assert_eq!(parse_number("-.25", 2).unwrap(), BigInt::from(-25));
+        assert_eq!(parse_number("3", 0).unwrap(), BigInt::from(3));
+    }
+
+    #[test]
+    fn rejects_excess_precision() {
+        assert!(parse_number("1.25", 1).is_err());
+        assert!(parse_number("1.x", 1).is_err());
+    }
+
+    #[test]
+    fn formats_small_decimal() {
+        assert_eq!(format_number(&BigInt::from(-5), 2, 10), "-0.05");
+        assert_eq!(format_number(&BigInt::from(1234), 2, 10), "12.34");
     }
 }
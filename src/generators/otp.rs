@@ -0,0 +1,137 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use rand::Rng;
+
+use super::binary::generate_bytes;
+use crate::encoding::{encode_base32, BASE32};
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// The HMAC algorithms that one-time passwords can be computed with.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OtpAlgorithm {
+    /// HMAC-SHA1, which every authenticator app supports
+    Sha1,
+    /// HMAC-SHA256
+    Sha256,
+    /// HMAC-SHA512
+    Sha512
+}
+
+impl OtpAlgorithm {
+    /// The name of the algorithm in an otpauth URI.
+    fn name(self) -> &'static str {
+        match self {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512"
+        }
+    }
+
+    /// The length of the secret in bytes, which matches the output of the hash function as RFC 4226 and RFC 6238 recommend.
+    pub fn secret_length(self) -> usize {
+        match self {
+            OtpAlgorithm::Sha1 => 20,
+            OtpAlgorithm::Sha256 => 32,
+            OtpAlgorithm::Sha512 => 64
+        }
+    }
+}
+
+/// Whether one-time passwords are derived from the time or from a counter.
+#[derive(Clone, Copy)]
+pub enum OtpKind {
+    /// Time-based, changing every `period` seconds
+    Totp { period: u64 },
+    /// Counter-based, starting from `counter`
+    Hotp { counter: u64 }
+}
+
+/// A newly-provisioned one-time password secret.
+pub struct OtpSecret {
+    /// The secret, encoded as unpadded Base32.
+    pub secret: Secret,
+    /// An otpauth URI that authenticator apps can import.
+    pub uri: Secret
+}
+
+/// Percent-encode every character of a URI component that isn't unreserved, as described by RFC 3986.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}")
+        })
+        .collect()
+}
+
+/// Generate a one-time password secret and describe it with an otpauth URI.
+pub fn generate_otp<R: Rng>(kind: OtpKind, issuer: Option<&str>, account: &str, algorithm: OtpAlgorithm, digits: u32, rng: &mut R) -> Result<OtpSecret> {
+    // A colon separates the issuer from the account in the label, so neither may contain one.
+    if issuer.is_some_and(|issuer| issuer.contains(':')) || account.contains(':') {
+        return Err(Error::InvalidArgument("The issuer and account name cannot contain a colon".into()));
+    }
+
+    let bytes = generate_bytes(algorithm.secret_length(), rng)?;
+    let secret = encode_base32(&bytes, BASE32, false);
+
+    let (scheme, parameter) = match kind {
+        OtpKind::Totp { period } => ("totp", format!("period={period}")),
+        OtpKind::Hotp { counter } => ("hotp", format!("counter={counter}"))
+    };
+    let (label, issuer) = match issuer {
+        Some(issuer) => (format!("{}:{}", percent_encode(issuer), percent_encode(account)), format!("&issuer={}", percent_encode(issuer))),
+        None => (percent_encode(account), String::new())
+    };
+    let mut uri = Secret::default();
+
+    // The URI is written straight into a secret, so the copy of the secret inside it is never left behind in a string.
+    write!(uri, "otpauth://{scheme}/{label}?secret=")?;
+    uri.extend_from_slice(&secret);
+    write!(uri, "{issuer}&algorithm={}&digits={digits}&{parameter}", algorithm.name())?;
+
+    Ok(OtpSecret { secret, uri })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn sizes_secret_for_algorithm() {
+        let totp = OtpKind::Totp { period: 30 };
+
+        assert_eq!(generate_otp(totp, None, "alice", OtpAlgorithm::Sha1, 6, &mut thread_rng()).unwrap().secret.len(), 32);
+        assert_eq!(generate_otp(totp, None, "alice", OtpAlgorithm::Sha256, 6, &mut thread_rng()).unwrap().secret.len(), 52);
+        assert_eq!(generate_otp(totp, None, "alice", OtpAlgorithm::Sha512, 6, &mut thread_rng()).unwrap().secret.len(), 103);
+    }
+
+    #[test]
+    fn formats_totp_uri() {
+        let otp = generate_otp(OtpKind::Totp { period: 30 }, Some("ACME Co"), "alice@example.com", OtpAlgorithm::Sha1, 6, &mut thread_rng()).unwrap();
+        let expected = format!("otpauth://totp/ACME%20Co:alice%40example.com?secret={}&issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30", from_utf8(&otp.secret).unwrap());
+
+        assert_eq!(otp.uri, expected);
+    }
+
+    #[test]
+    fn formats_hotp_uri() {
+        let otp = generate_otp(OtpKind::Hotp { counter: 7 }, None, "alice", OtpAlgorithm::Sha256, 8, &mut thread_rng()).unwrap();
+        let expected = format!("otpauth://hotp/alice?secret={}&algorithm=SHA256&digits=8&counter=7", from_utf8(&otp.secret).unwrap());
+
+        assert_eq!(otp.uri, expected);
+    }
+
+    #[test]
+    fn rejects_colon_in_label() {
+        let result = generate_otp(OtpKind::Totp { period: 30 }, Some("ACME:Corp"), "alice", OtpAlgorithm::Sha1, 6, &mut thread_rng());
+
+        assert!(matches!(result, Err(Error::InvalidArgument(_))))
+    }
+}
@@ -1,27 +1,34 @@
-use rand::{distributions::Slice, thread_rng, Rng};
+use rand::{distributions::Slice, Rng};
+
+use crate::error::{Error, Result};
+use crate::secret::Secret;
 
 /// Generate a passphrase.
-pub fn generate_passphrase(wordlist: &[String], separator: &str, length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
-
-    let distribution = Slice::new(wordlist).unwrap();
-
-    thread_rng()
-        .sample_iter(distribution)
-        .take(length)
-        .map(|value| value.to_owned())
-        .collect::<Vec<String>>()
-        .join(separator)
-        .into_bytes()
+pub fn generate_passphrase<R: Rng>(wordlist: &[String], separator: &str, length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
+
+    let distribution = Slice::new(wordlist)
+        .map_err(|_| Error::EmptyAlphabet("The wordlist contains no words".into()))?;
+    let mut passphrase = Secret::default();
+
+    for (index, word) in rng.sample_iter(distribution).take(length).enumerate() {
+        if index > 0 { passphrase.extend_from_slice(separator.as_bytes()); }
+
+        passphrase.extend_from_slice(word.as_bytes());
+    }
+
+    Ok(passphrase)
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::from_utf8;
 
+    use rand::thread_rng;
+
     use super::*;
 
-    fn word_count(buffer: &Vec<u8>) -> usize {
+    fn word_count(buffer: &[u8]) -> usize {
         from_utf8(buffer)
             .unwrap()
             .split(' ')
@@ -35,7 +42,7 @@ mod tests {
     #[test]
     fn generates_ten_thousand_word_passphrase() {
         let wordlist = load_test_wordlist();
-        let bytes = generate_passphrase(&wordlist, &" ".to_string(), 10000);
+        let bytes = generate_passphrase(&wordlist, " ", 10000, &mut thread_rng()).unwrap();
         let count = word_count(&bytes);
 
         assert_eq!(count, 10000)
@@ -44,7 +51,7 @@ mod tests {
     #[test]
     fn generates_hundred_thousand_word_passphrase() {
         let wordlist = load_test_wordlist();
-        let bytes = generate_passphrase(&wordlist, &" ".to_string(), 100000);
+        let bytes = generate_passphrase(&wordlist, " ", 100000, &mut thread_rng()).unwrap();
         let count = word_count(&bytes);
 
         assert_eq!(count, 100000)
@@ -53,16 +60,16 @@ mod tests {
     #[test]
     fn generates_empty_passphrase() {
         let wordlist = load_test_wordlist();
-        let bytes = generate_passphrase(&wordlist, &" ".to_string(), 0);
+        let bytes = generate_passphrase(&wordlist, " ", 0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
 
     #[test]
-    #[should_panic]
-    fn empty_wordlist_panics() {
+    fn empty_wordlist_is_rejected() {
         let wordlist = Vec::<String>::new();
+        let result = generate_passphrase(&wordlist, " ", 1, &mut thread_rng());
 
-        generate_passphrase(&wordlist, &" ".to_string(), 1);
+        assert!(matches!(result, Err(Error::EmptyAlphabet(_))))
     }
 }
@@ -1,27 +1,33 @@
-use rand::{thread_rng, Rng, distributions::Slice};
+use rand::{Rng, distributions::Slice};
 
-pub fn generate_password(character_set: &[char], length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
+use crate::error::{Error, Result};
+use crate::secret::Secret;
 
-    let distribution = Slice::new(character_set).unwrap();
+pub fn generate_password<R: Rng>(character_set: &[char], length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
 
-    thread_rng()
+    let distribution = Slice::new(character_set)
+        .map_err(|_| Error::EmptyAlphabet("The character set contains no characters".into()))?;
+    let password = rng
         .sample_iter(distribution)
         .take(length)
-        .collect::<String>()
-        .into_bytes()
+        .collect::<Secret>();
+
+    Ok(password)
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::from_utf8;
 
+    use rand::thread_rng;
+
     use super::*;
 
     #[test]
     fn generates_ten_thousand_character_password() {
         let character_set: Vec<char> = "!@*-_.0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().collect();
-        let bytes = generate_password(&character_set, 10000);
+        let bytes = generate_password(&character_set, 10000, &mut thread_rng()).unwrap();
         let string = from_utf8(&bytes).unwrap();
 
         assert_eq!(string.chars().count(), 10000)
@@ -30,8 +36,15 @@ mod tests {
     #[test]
     fn generates_empty_password() {
         let character_set: Vec<char> = "!@*-_.0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().collect();
-        let bytes = generate_password(&character_set, 0);
+        let bytes = generate_password(&character_set, 0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
+
+    #[test]
+    fn empty_character_set_is_rejected() {
+        let result = generate_password(&[], 1, &mut thread_rng());
+
+        assert!(matches!(result, Err(Error::EmptyAlphabet(_))))
+    }
 }
@@ -0,0 +1,136 @@
+use clap::ValueEnum;
+use crc32fast::hash;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use super::binary::generate_bytes;
+use crate::encoding::{encode_fixed_radix, BASE62};
+use crate::error::Result;
+use crate::secret::Secret;
+
+/// The number of bytes of a SHA-256 digest that are kept as a checksum.
+const SHA256_CHECKSUM_LENGTH: usize = 6;
+
+/// The algorithms that the checksum at the end of a token can be calculated with.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TokenChecksum {
+    /// A CRC-32 checksum, encoded as 6 Base62 characters, like GitHub's tokens
+    Crc32,
+    /// The first 6 bytes of a SHA-256 digest, encoded as 9 Base62 characters
+    Sha256
+}
+
+impl TokenChecksum {
+    /// The number of Base62 characters that the checksum is encoded as.
+    fn width(self) -> usize {
+        match self {
+            TokenChecksum::Crc32 => 6,
+            TokenChecksum::Sha256 => 9
+        }
+    }
+
+    /// Calculate the checksum of the random body of a token, which leaves out the prefix just as GitHub's tokens do.
+    fn compute(self, body: &[u8]) -> Secret {
+        match self {
+            TokenChecksum::Crc32 => encode_fixed_radix(&hash(body).to_be_bytes(), BASE62, self.width()),
+            TokenChecksum::Sha256 => encode_fixed_radix(&Sha256::digest(body)[..SHA256_CHECKSUM_LENGTH], BASE62, self.width())
+        }
+    }
+}
+
+/// Separate a prefix from the body of a token with an underscore, unless it already ends with one.
+fn separate(prefix: &str) -> String {
+    if prefix.is_empty() || prefix.ends_with('_') { prefix.to_owned() }
+    else { format!("{prefix}_") }
+}
+
+/// Generate a token that consists of a prefix, `length` random bytes encoded as Base62 and a checksum of the encoded bytes.
+///
+/// The body is always the same number of characters long, so every token with the same options has the same length.
+pub fn generate_token<R: Rng>(prefix: &str, length: usize, checksum: TokenChecksum, rng: &mut R) -> Result<Secret> {
+    let width = (length as f64 * 8.0 / 62_f64.log2()).ceil() as usize;
+    let bytes = generate_bytes(length, rng)?;
+    let body = encode_fixed_radix(&bytes, BASE62, width);
+    let mut token = Secret::from(separate(prefix));
+
+    token.extend_from_slice(&body);
+
+    let suffix = checksum.compute(&body);
+
+    token.extend_from_slice(&suffix);
+
+    Ok(token)
+}
+
+/// Check whether a token ends with a valid checksum, and begins with the prefix if one is given.
+pub fn verify_token(token: &str, prefix: Option<&str>, checksum: TokenChecksum) -> bool {
+    let bytes = token.as_bytes();
+
+    if bytes.len() <= checksum.width() { return false; }
+    if prefix.is_some_and(|prefix| !token.starts_with(&separate(prefix))) { return false; }
+
+    let (payload, suffix) = bytes.split_at(bytes.len() - checksum.width());
+
+    // Base62 has no underscores, so the body is everything after the last one.
+    let body = match payload.iter().rposition(|byte| *byte == b'_') {
+        Some(position) => &payload[position + 1..],
+        None => payload
+    };
+
+    checksum.compute(body) == suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn generates_prefixed_token() {
+        let bytes = generate_token("acme_live", 30, TokenChecksum::Crc32, &mut thread_rng()).unwrap();
+        let token = from_utf8(&bytes).unwrap();
+
+        assert!(token.starts_with("acme_live_"));
+        assert_eq!(token.len(), "acme_live_".len() + 41 + 6);
+        assert!(token["acme_live_".len()..].bytes().all(|byte| BASE62.contains(&byte)));
+    }
+
+    #[test]
+    fn verifies_generated_tokens() {
+        for checksum in [TokenChecksum::Crc32, TokenChecksum::Sha256] {
+            let bytes = generate_token("ghp", 30, checksum, &mut thread_rng()).unwrap();
+            let token = from_utf8(&bytes).unwrap();
+
+            assert!(verify_token(token, None, checksum));
+            assert!(verify_token(token, Some("ghp_"), checksum));
+            assert!(!verify_token(token, Some("acme"), checksum));
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_tokens() {
+        let bytes = generate_token("acme", 30, TokenChecksum::Crc32, &mut thread_rng()).unwrap();
+        let mut token = from_utf8(&bytes).unwrap().to_owned();
+        let replacement = if token.as_bytes()[10] == b'a' { "b" } else { "a" };
+
+        token.replace_range(10..11, replacement);
+
+        assert!(!verify_token(&token, None, TokenChecksum::Crc32));
+        assert!(!verify_token("short", None, TokenChecksum::Crc32));
+    }
+
+    #[test]
+    fn checksums_only_the_body() {
+        let bytes = generate_token("acme_live", 30, TokenChecksum::Crc32, &mut thread_rng()).unwrap();
+        let token = from_utf8(&bytes).unwrap();
+        let (payload, suffix) = token.split_at(token.len() - 6);
+        let body = &payload["acme_live_".len()..];
+
+        assert_eq!(encode_fixed_radix(&hash(body.as_bytes()).to_be_bytes(), BASE62, 6), suffix);
+        assert!(verify_token(&format!("ghp_{body}{suffix}"), Some("ghp"), TokenChecksum::Crc32));
+        assert!(verify_token(&format!("{body}{suffix}"), None, TokenChecksum::Crc32));
+    }
+}
@@ -1,7 +1,10 @@
-use rand::{random, thread_rng, Rng};
-use rand::rngs::ThreadRng;
+use rand::Rng;
 use rand::distributions::{Distribution, Standard};
 use rand::seq::SliceRandom;
+use zeroize::Zeroizing;
+
+use crate::error::Result;
+use crate::secret::Secret;
 
 const VOWELS: [char; 6] = [
     'a', 'e', 'i',
@@ -15,64 +18,85 @@ const CONSONANTS: [char; 20] = [
     't', 'v', 'w', 'x', 'z'
 ];
 
-fn choose_random_vowel(rng: &mut ThreadRng) -> &char {
+fn choose_random_vowel<R: Rng>(rng: &mut R) -> &char {
     VOWELS.choose(rng).unwrap()
 }
 
-fn choose_random_consonant(rng: &mut ThreadRng) -> &char {
+fn choose_random_consonant<R: Rng>(rng: &mut R) -> &char {
     CONSONANTS.choose(rng).unwrap()
 }
 
-fn add_vowel(input: &mut Vec<char>, rng: &mut ThreadRng) {
+fn add_vowel<R: Rng>(input: &mut Vec<char>, rng: &mut R) {
     let value = choose_random_vowel(rng);
 
     input.push(*value);
 }
 
-fn add_consonant(input: &mut Vec<char>, rng: &mut ThreadRng) {
+fn add_consonant<R: Rng>(input: &mut Vec<char>, rng: &mut R) {
     let value = choose_random_consonant(rng);
 
     input.push(*value);
 }
 
-fn create_closed_syllable(rng: &mut ThreadRng) -> Vec<char> {
-    vec![
-        *choose_random_consonant(rng),
-        *choose_random_vowel(rng),
-        *choose_random_consonant(rng)
-    ]
+fn add_closed_syllable<R: Rng>(input: &mut Vec<char>, rng: &mut R) {
+    add_consonant(input, rng);
+    add_vowel(input, rng);
+    add_consonant(input, rng);
 }
 
-fn create_open_syllable(rng: &mut ThreadRng) -> Vec<char> {
-    vec![
-        *choose_random_consonant(rng),
-        *choose_random_vowel(rng)
-    ]
+fn add_open_syllable<R: Rng>(input: &mut Vec<char>, rng: &mut R) {
+    add_consonant(input, rng);
+    add_vowel(input, rng);
 }
 
 enum SyllableType {
-    CLOSED,
-    OPEN
+    Closed,
+    Open
 }
 
 impl Distribution<SyllableType> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> SyllableType {
         match rng.gen_range(0..=1) {
-            0 => SyllableType::CLOSED,
-            _ => SyllableType::OPEN
+            0 => SyllableType::Closed,
+            _ => SyllableType::Open
         }
     }
 }
 
+/// The number of bits of entropy in a username of `length` alternating vowels and consonants.
+pub fn simple_username_entropy(length: usize) -> f64 {
+    if length == 0 { return 0.0; }
+
+    let vowel = (VOWELS.len() as f64).log2();
+    let consonant = (CONSONANTS.len() as f64).log2();
+    let vowels = |start: usize| (start..length).step_by(2).count() as f64;
+
+    // One bit chooses whether to start with a vowel, after which every character alternates.
+    let starting_with_vowel = vowels(0) * vowel + vowels(1) * consonant;
+    let starting_with_consonant = vowels(1) * vowel + vowels(0) * consonant;
+
+    1.0 + (starting_with_vowel + starting_with_consonant) / 2.0
+}
+
+/// The number of bits of entropy in a username of `length` syllables, each of which is closed or open with equal probability.
+pub fn complex_username_entropy(length: usize) -> f64 {
+    let vowel = (VOWELS.len() as f64).log2();
+    let consonant = (CONSONANTS.len() as f64).log2();
+    let closed = 2.0 * consonant + vowel;
+    let open = consonant + vowel;
+
+    length as f64 * (1.0 + (closed + open) / 2.0)
+}
+
 /// Generate a simple pronounceable username by alternating random vowels and consonants.
 ///
 /// Usernames created in this fashion are guaranteed to be pronouncable,
 /// but are likely to be flagged as suspicious by automated tools and may not be aesthetically pleasing.
-pub fn generate_simple_username(capitalize: bool, length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
+pub fn generate_simple_username<R: Rng>(capitalize: bool, length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
 
-    let mut output: Vec<char> = Vec::new();
-    let rng = &mut thread_rng();
+    // Reserve every character up front, so that the username is never left behind in a smaller buffer.
+    let mut output = Zeroizing::new(Vec::<char>::with_capacity(length));
     let start = rng.gen_bool(1.0 / 2.0);
 
     // Flip a coin to choose between starting with a vowel or a consonant.
@@ -82,7 +106,7 @@ pub fn generate_simple_username(capitalize: bool, length: usize) -> Vec<u8> {
     }
 
     // If only one character is needed, then we are done.
-    if length == 1 { return output.iter().collect::<String>().into_bytes(); }
+    if length == 1 { return Ok(output.iter().collect()); }
 
     // Alternate between adding consonants and vowels
     for index in 0..(length - 1) {
@@ -95,47 +119,42 @@ pub fn generate_simple_username(capitalize: bool, length: usize) -> Vec<u8> {
 
     if capitalize { output[0].make_ascii_uppercase(); }
 
-    output.iter()
-        .collect::<String>()
-        .into_bytes()
+    Ok(output.iter().collect())
 }
 
 /// Generate a pronounceable username from random syllables.
 ///
 /// Syllabic usernames are less likely to be flagged as suspicious by automated tools,
 /// and may be more aesthetically pleasing.
-pub fn generate_complex_username(capitalize: bool, length: usize) -> Vec<u8> {
-    if length == 0 { return Vec::<u8>::new(); }
+pub fn generate_complex_username<R: Rng>(capitalize: bool, length: usize, rng: &mut R) -> Result<Secret> {
+    if length == 0 { return Ok(Secret::default()); }
 
-    let rng = &mut thread_rng();
-    let mut output = Vec::<char>::new();
+    let mut output = Zeroizing::new(Vec::<char>::with_capacity(length * 3));
 
     for _ in 0..length {
         // Generate a random syllable of a random type.
-        let syllable = match random::<SyllableType>() {
-            SyllableType::CLOSED => create_closed_syllable(rng),
-            SyllableType::OPEN => create_open_syllable(rng)
-        };
-
-        output.extend(syllable);
+        match rng.gen::<SyllableType>() {
+            SyllableType::Closed => add_closed_syllable(&mut output, rng),
+            SyllableType::Open => add_open_syllable(&mut output, rng)
+        }
     }
 
     if capitalize { output[0].make_ascii_uppercase(); }
 
-    output.iter()
-        .collect::<String>()
-        .into_bytes()
+    Ok(output.iter().collect())
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::from_utf8;
 
-    use super::{generate_simple_username, generate_complex_username};
+    use rand::thread_rng;
+
+    use super::*;
 
     #[test]
     fn generates_ten_thousand_character_simple_username() {
-        let bytes = generate_simple_username(false, 10000);
+        let bytes = generate_simple_username(false, 10000, &mut thread_rng()).unwrap();
         let string = from_utf8(&bytes).unwrap();
 
         assert_eq!(string.chars().count(), 10000)
@@ -143,14 +162,14 @@ mod tests {
 
     #[test]
     fn generates_empty_simple_username() {
-        let bytes = generate_simple_username(false, 0);
+        let bytes = generate_simple_username(false, 0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
 
     #[test]
     fn generates_ten_thousand_syllable_complex_username() {
-        let bytes = generate_complex_username(false, 10000);
+        let bytes = generate_complex_username(false, 10000, &mut thread_rng()).unwrap();
         let string = from_utf8(&bytes).unwrap();
         let length = string.chars().count();
         let range = 20000..=30000;
@@ -160,8 +179,18 @@ mod tests {
 
     #[test]
     fn generates_empty_complex_username() {
-        let bytes = generate_complex_username(false, 0);
+        let bytes = generate_complex_username(false, 0, &mut thread_rng()).unwrap();
 
         assert_eq!(bytes.len(), 0)
     }
+
+    #[test]
+    fn calculates_username_entropy() {
+        let vowel = 6_f64.log2();
+        let consonant = 20_f64.log2();
+
+        assert_eq!(simple_username_entropy(0), 0.0);
+        assert!((simple_username_entropy(2) - (1.0 + vowel + consonant)).abs() < 1e-9);
+        assert!((complex_username_entropy(1) - (1.0 + (3.0 * consonant + 2.0 * vowel) / 2.0)).abs() < 1e-9);
+    }
 }
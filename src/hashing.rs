@@ -0,0 +1,299 @@
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::{Argon2, Version};
+use clap::ValueEnum;
+use pbkdf2::Pbkdf2;
+use rand::Rng;
+use scrypt::Scrypt;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+use crate::yescrypt::yescrypt;
+
+/// The number of random bytes in every salt.
+const SALT_LENGTH: usize = 16;
+
+/// The alphabet of crypt(3), which SHA-512-crypt uses for both its salt and its output.
+pub const CRYPT_ALPHABET: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The number of rounds of SHA-512-crypt when none are given, which is left out of the hash.
+const SHA512_CRYPT_DEFAULT_ROUNDS: u32 = 5000;
+
+/// The order in which SHA-512-crypt encodes the bytes of its final digest, three at a time.
+const SHA512_CRYPT_PERMUTATION: [(usize, usize, usize); 21] = [
+    (0, 21, 42), (22, 43, 1), (44, 2, 23), (3, 24, 45), (25, 46, 4), (47, 5, 26), (6, 27, 48),
+    (28, 49, 7), (50, 8, 29), (9, 30, 51), (31, 52, 10), (53, 11, 32), (12, 33, 54), (34, 55, 13),
+    (56, 14, 35), (15, 36, 57), (37, 58, 16), (59, 17, 38), (18, 39, 60), (40, 61, 19), (62, 20, 41)
+];
+
+/// The cost of yescrypt when none is given, which is also libxcrypt's default and uses 16 MiB.
+const YESCRYPT_DEFAULT_COST: u32 = 5;
+
+/// The longest password that bcrypt can hash without ignoring part of it.
+const BCRYPT_MAXIMUM_LENGTH: usize = 72;
+
+/// The algorithms that generated passwords can be hashed with.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum HashAlgorithm {
+    /// Argon2id as a PHC string, which is the best choice for new applications
+    Argon2id,
+    /// bcrypt as a $2b$ string
+    Bcrypt,
+    /// scrypt as a PHC string
+    Scrypt,
+    /// SHA-512-crypt as a $6$ string, which /etc/shadow accepts on every Linux distribution
+    Sha512Crypt,
+    /// yescrypt as a $y$ string, which is the default for /etc/shadow on most recent Linux distributions
+    Yescrypt,
+    /// PBKDF2 with HMAC-SHA256 as a PHC string
+    Pbkdf2Sha256
+}
+
+/// A hash algorithm along with its cost parameters, which have all been checked.
+#[derive(Clone)]
+pub struct HashSettings {
+    algorithm: HashAlgorithm,
+    cost: u32,
+    memory: u32,
+    parallelism: u32
+}
+
+impl HashSettings {
+    /// Choose the cost parameters of a hash algorithm, using the recommended value for any that aren't given.
+    ///
+    /// The cost is the number of iterations for Argon2id, PBKDF2 and SHA-512-crypt, its base-2 logarithm for bcrypt and scrypt, and libxcrypt's cost for yescrypt.
+    /// Memory only applies to Argon2id, and parallelism only applies to Argon2id and scrypt.
+    pub fn new(algorithm: HashAlgorithm, cost: Option<u32>, memory: Option<u32>, parallelism: Option<u32>) -> Result<Self> {
+        let name = algorithm.to_possible_value().unwrap().get_name().to_owned();
+
+        if memory.is_some() && !matches!(algorithm, HashAlgorithm::Argon2id) {
+            return Err(Error::InvalidArgument(format!("The memory cost cannot be set for {name}")));
+        }
+
+        if parallelism.is_some() && !matches!(algorithm, HashAlgorithm::Argon2id | HashAlgorithm::Scrypt) {
+            return Err(Error::InvalidArgument(format!("The parallelism cannot be set for {name}")));
+        }
+
+        let default_cost = match algorithm {
+            HashAlgorithm::Argon2id => argon2::Params::DEFAULT_T_COST,
+            HashAlgorithm::Bcrypt => bcrypt::DEFAULT_COST,
+            HashAlgorithm::Scrypt => scrypt::Params::RECOMMENDED_LOG_N as u32,
+            HashAlgorithm::Sha512Crypt => SHA512_CRYPT_DEFAULT_ROUNDS,
+            HashAlgorithm::Yescrypt => YESCRYPT_DEFAULT_COST,
+            HashAlgorithm::Pbkdf2Sha256 => pbkdf2::Params::RECOMMENDED_ROUNDS as u32
+        };
+        let default_parallelism = match algorithm {
+            HashAlgorithm::Scrypt => scrypt::Params::RECOMMENDED_P,
+            _ => argon2::Params::DEFAULT_P_COST
+        };
+
+        let settings = HashSettings {
+            algorithm,
+            cost: cost.unwrap_or(default_cost),
+            memory: memory.unwrap_or(argon2::Params::DEFAULT_M_COST),
+            parallelism: parallelism.unwrap_or(default_parallelism)
+        };
+
+        let valid = match algorithm {
+            HashAlgorithm::Argon2id => settings.argon2_params().is_ok(),
+            HashAlgorithm::Bcrypt => (4..=31).contains(&settings.cost),
+            HashAlgorithm::Scrypt => settings.scrypt_params().is_ok(),
+            HashAlgorithm::Sha512Crypt => (1000..=999_999_999).contains(&settings.cost),
+            HashAlgorithm::Yescrypt => (1..=11).contains(&settings.cost),
+            HashAlgorithm::Pbkdf2Sha256 => settings.cost > 0
+        };
+
+        if !valid { return Err(Error::InvalidArgument(format!("The cost parameters are out of range for {name}"))); }
+
+        Ok(settings)
+    }
+
+    fn argon2_params(&self) -> std::result::Result<argon2::Params, argon2::Error> {
+        argon2::Params::new(self.memory, self.cost, self.parallelism, None)
+    }
+
+    fn scrypt_params(&self) -> std::result::Result<scrypt::Params, scrypt::errors::InvalidParams> {
+        let log_n = u8::try_from(self.cost).map_err(|_| scrypt::errors::InvalidParams)?;
+
+        scrypt::Params::new(log_n, scrypt::Params::RECOMMENDED_R, self.parallelism, scrypt::Params::RECOMMENDED_LEN)
+    }
+
+    /// Hash a password with a new random salt, returning a PHC or crypt(3) string.
+    pub fn hash<R: Rng>(&self, password: &[u8], rng: &mut R) -> Result<String> {
+        let mut salt = [0_u8; SALT_LENGTH];
+
+        rng.fill_bytes(&mut salt);
+
+        let phc_salt = SaltString::encode_b64(&salt).map_err(|error| Error::Internal(error.to_string()))?;
+
+        let hash = match self.algorithm {
+            HashAlgorithm::Argon2id => {
+                let params = self.argon2_params().map_err(|error| Error::Internal(error.to_string()))?;
+
+                Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password(password, &phc_salt)
+                    .map(|hash| hash.to_string())
+            }
+            HashAlgorithm::Bcrypt => {
+                if password.len() > BCRYPT_MAXIMUM_LENGTH {
+                    return Err(Error::InvalidArgument(format!("bcrypt ignores everything after the first {BCRYPT_MAXIMUM_LENGTH} bytes of a password")));
+                }
+
+                let hash = bcrypt::hash_with_salt(password, self.cost, salt).map_err(|error| Error::Internal(error.to_string()))?;
+
+                Ok(hash.format_for_version(bcrypt::Version::TwoB))
+            }
+            HashAlgorithm::Scrypt => {
+                let params = self.scrypt_params().map_err(|error| Error::Internal(error.to_string()))?;
+
+                Scrypt
+                    .hash_password_customized(password, None, None, params, &phc_salt)
+                    .map(|hash| hash.to_string())
+            }
+            HashAlgorithm::Sha512Crypt => {
+                let salt = salt.iter().map(|byte| CRYPT_ALPHABET[(byte & 0x3F) as usize]).collect::<Vec<u8>>();
+
+                Ok(sha512_crypt(password, &salt, self.cost))
+            }
+            HashAlgorithm::Yescrypt => Ok(yescrypt(password, &salt, self.cost)),
+            HashAlgorithm::Pbkdf2Sha256 => {
+                let params = pbkdf2::Params { rounds: self.cost, output_length: 32 };
+
+                Pbkdf2
+                    .hash_password_customized(password, Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()), None, params, &phc_salt)
+                    .map(|hash| hash.to_string())
+            }
+        };
+
+        hash.map_err(|error| Error::Internal(error.to_string()))
+    }
+}
+
+/// Repeat a digest until it is `length` bytes long, as SHA-512-crypt does to hide the length of the password and salt.
+fn stretch(digest: &[u8], length: usize) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(digest.iter().cycle().take(length).copied().collect())
+}
+
+/// Hash a password with SHA-512-crypt, exactly as described in Ulrich Drepper's specification.
+///
+/// The salt must already be made of characters from the crypt(3) alphabet, and at most 16 of them.
+fn sha512_crypt(password: &[u8], salt: &[u8], rounds: u32) -> String {
+    let alternate = Sha512::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+
+    let mut hasher = Sha512::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(stretch(&alternate, password.len()));
+    let mut length = password.len();
+
+    while length > 0 {
+        if length & 1 == 1 { hasher.update(alternate); } else { hasher.update(password); }
+
+        length >>= 1;
+    }
+
+    let mut digest = hasher.finalize();
+
+    let password_digest = password.iter().fold(Sha512::new(), |hasher, _| hasher.chain_update(password)).finalize();
+    let salt_digest = (0..16 + digest[0] as usize).fold(Sha512::new(), |hasher, _| hasher.chain_update(salt)).finalize();
+    let password_sequence = stretch(&password_digest, password.len());
+    let salt_sequence = stretch(&salt_digest, salt.len());
+
+    for round in 0..rounds {
+        let mut hasher = Sha512::new();
+
+        if round % 2 == 1 { hasher.update(password_sequence.as_slice()); } else { hasher.update(digest); }
+        if round % 3 != 0 { hasher.update(salt_sequence.as_slice()); }
+        if round % 7 != 0 { hasher.update(password_sequence.as_slice()); }
+        if round % 2 == 1 { hasher.update(digest); } else { hasher.update(password_sequence.as_slice()); }
+
+        digest = hasher.finalize();
+    }
+
+    let mut encoded = String::with_capacity(86);
+    let mut push = |value: u32, characters: usize| {
+        for index in 0..characters {
+            encoded.push(CRYPT_ALPHABET[((value >> (index * 6)) & 0x3F) as usize] as char);
+        }
+    };
+
+    for (first, second, third) in SHA512_CRYPT_PERMUTATION {
+        push(((digest[first] as u32) << 16) | ((digest[second] as u32) << 8) | digest[third] as u32, 4);
+    }
+
+    push(digest[63] as u32, 2);
+
+    let salt = String::from_utf8_lossy(salt);
+
+    if rounds == SHA512_CRYPT_DEFAULT_ROUNDS { format!("$6${salt}${encoded}") }
+    else { format!("$6$rounds={rounds}${salt}${encoded}") }
+}
+
+#[cfg(test)]
+mod tests {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn matches_sha512_crypt_test_vectors() {
+        // The first vector is from the specification, and the rest were calculated independently with glibc.
+        assert_eq!(
+            sha512_crypt(b"Hello world!", b"saltstring", 5000),
+            "$6$saltstring$svn8UoSVapNtMuq1ukKS4tPQd8iKwSMHWjl/O817G3uBnIFNjnQJuesI68u4OTLiBFdcbYEdFCoEOfaS35inz1"
+        );
+        assert_eq!(
+            sha512_crypt(b"Hello world!", b"saltstringsaltst", 10000),
+            "$6$rounds=10000$saltstringsaltst$OW1/O6BYHV6BcXZu8QVeXbDWra3Oeqh0sbHbbMCVNSnCM/UrjmM0Dp8vOuZeHBy/YTBmSK6H9qs/y3RnOaw5v."
+        );
+        assert_eq!(
+            sha512_crypt(b"", b"abc", 1000),
+            "$6$rounds=1000$abc$noBronbzNMcAtG61/dMHzc1H.fuLjHArF9.wCx8LbkVDMMEuChQCPee28tiPcXHL/CMNpCqo6OPTyoEpoCMsY/"
+        );
+    }
+
+    #[test]
+    fn hashes_verify_against_password() {
+        let password = b"correct horse battery staple";
+        let argon2 = HashSettings::new(HashAlgorithm::Argon2id, Some(1), Some(64), None).unwrap();
+        let scrypt = HashSettings::new(HashAlgorithm::Scrypt, Some(4), None, None).unwrap();
+        let pbkdf2 = HashSettings::new(HashAlgorithm::Pbkdf2Sha256, Some(1000), None, None).unwrap();
+
+        let hash = argon2.hash(password, &mut thread_rng()).unwrap();
+
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert!(Argon2::default().verify_password(password, &PasswordHash::new(&hash).unwrap()).is_ok());
+
+        let hash = scrypt.hash(password, &mut thread_rng()).unwrap();
+
+        assert!(Scrypt.verify_password(password, &PasswordHash::new(&hash).unwrap()).is_ok());
+
+        let hash = pbkdf2.hash(password, &mut thread_rng()).unwrap();
+
+        assert!(hash.starts_with("$pbkdf2-sha256$i=1000,l=32$"));
+        assert!(Pbkdf2.verify_password(password, &PasswordHash::new(&hash).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn hashes_with_bcrypt() {
+        let settings = HashSettings::new(HashAlgorithm::Bcrypt, Some(4), None, None).unwrap();
+        let hash = settings.hash(b"hunter2", &mut thread_rng()).unwrap();
+
+        assert!(hash.starts_with("$2b$04$"));
+        assert!(bcrypt::verify(b"hunter2", &hash).unwrap());
+        assert!(matches!(settings.hash(&[b'a'; 73], &mut thread_rng()), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_cost_parameters() {
+        assert!(matches!(HashSettings::new(HashAlgorithm::Bcrypt, Some(32), None, None), Err(Error::InvalidArgument(_))));
+        assert!(matches!(HashSettings::new(HashAlgorithm::Sha512Crypt, Some(999), None, None), Err(Error::InvalidArgument(_))));
+        assert!(matches!(HashSettings::new(HashAlgorithm::Argon2id, None, Some(1), None), Err(Error::InvalidArgument(_))));
+        assert!(matches!(HashSettings::new(HashAlgorithm::Bcrypt, None, Some(65536), None), Err(Error::InvalidArgument(_))));
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+use argon2::{Argon2, Version};
+use clap::ValueEnum;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// The number of random bytes in a salt that is generated because none was given.
+pub const SALT_LENGTH: usize = 16;
+
+/// The passphrase and salt that keys are derived from while calibrating, which have no effect on how long it takes.
+const CALIBRATION_INPUT: &[u8; 16] = b"ciphergen kdfcal";
+
+/// The most memory in KiB that calibration will give to Argon2id, unless a different limit is set.
+const CALIBRATION_MEMORY: u32 = 1024 * 1024;
+
+/// The largest base-2 logarithm of N that calibration will choose for scrypt, which uses 1 GiB of memory.
+const CALIBRATION_LOG_N: u32 = 20;
+
+/// The number of PBKDF2 iterations that are timed to estimate how many fit into the target duration.
+const CALIBRATION_ROUNDS: u32 = 10_000;
+
+/// The functions that keys can be derived with.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum KdfAlgorithm {
+    /// Argon2id, which is the best choice for passphrases
+    Argon2id,
+    /// scrypt with r=8
+    Scrypt,
+    /// PBKDF2 with HMAC-SHA256
+    Pbkdf2,
+    /// HKDF with SHA-256, which is only suitable for input that is already random, such as another key
+    Hkdf
+}
+
+/// A key derivation function along with its parameters, which have all been checked.
+#[derive(Clone)]
+pub struct KdfSettings {
+    algorithm: KdfAlgorithm,
+    cost: u32,
+    memory: u32,
+    parallelism: u32,
+    info: Vec<u8>
+}
+
+impl KdfSettings {
+    /// Choose the parameters of a key derivation function, using the recommended value for any that aren't given.
+    ///
+    /// The cost is the number of iterations for Argon2id and PBKDF2, and the base-2 logarithm of N for scrypt.
+    /// Memory only applies to Argon2id, parallelism only applies to Argon2id and scrypt, and context information only applies to HKDF.
+    pub fn new(algorithm: KdfAlgorithm, cost: Option<u32>, memory: Option<u32>, parallelism: Option<u32>, info: Option<Vec<u8>>) -> Result<Self> {
+        let name = algorithm.to_possible_value().unwrap().get_name().to_owned();
+
+        if cost.is_some() && matches!(algorithm, KdfAlgorithm::Hkdf) {
+            return Err(Error::InvalidArgument(format!("The cost cannot be set for {name}")));
+        }
+
+        if memory.is_some() && !matches!(algorithm, KdfAlgorithm::Argon2id) {
+            return Err(Error::InvalidArgument(format!("The memory cost cannot be set for {name}")));
+        }
+
+        if parallelism.is_some() && !matches!(algorithm, KdfAlgorithm::Argon2id | KdfAlgorithm::Scrypt) {
+            return Err(Error::InvalidArgument(format!("The parallelism cannot be set for {name}")));
+        }
+
+        if info.is_some() && !matches!(algorithm, KdfAlgorithm::Hkdf) {
+            return Err(Error::InvalidArgument(format!("Context information cannot be given to {name}")));
+        }
+
+        let default_cost = match algorithm {
+            KdfAlgorithm::Argon2id => argon2::Params::DEFAULT_T_COST,
+            KdfAlgorithm::Scrypt => scrypt::Params::RECOMMENDED_LOG_N as u32,
+            KdfAlgorithm::Pbkdf2 => pbkdf2::Params::RECOMMENDED_ROUNDS as u32,
+            KdfAlgorithm::Hkdf => 0
+        };
+        let default_parallelism = match algorithm {
+            KdfAlgorithm::Scrypt => scrypt::Params::RECOMMENDED_P,
+            _ => argon2::Params::DEFAULT_P_COST
+        };
+
+        let settings = KdfSettings {
+            algorithm,
+            cost: cost.unwrap_or(default_cost),
+            memory: memory.unwrap_or(argon2::Params::DEFAULT_M_COST),
+            parallelism: parallelism.unwrap_or(default_parallelism),
+            info: info.unwrap_or_default()
+        };
+
+        let valid = match algorithm {
+            KdfAlgorithm::Argon2id => settings.argon2_params(None).is_ok(),
+            KdfAlgorithm::Scrypt => settings.scrypt_params().is_ok(),
+            KdfAlgorithm::Pbkdf2 => settings.cost > 0,
+            KdfAlgorithm::Hkdf => true
+        };
+
+        if !valid { return Err(Error::InvalidArgument(format!("The parameters are out of range for {name}"))); }
+
+        Ok(settings)
+    }
+
+    fn argon2_params(&self, length: Option<usize>) -> std::result::Result<argon2::Params, argon2::Error> {
+        argon2::Params::new(self.memory, self.cost, self.parallelism, length)
+    }
+
+    fn scrypt_params(&self) -> std::result::Result<scrypt::Params, scrypt::errors::InvalidParams> {
+        let log_n = u8::try_from(self.cost).map_err(|_| scrypt::errors::InvalidParams)?;
+
+        scrypt::Params::new(log_n, scrypt::Params::RECOMMENDED_R, self.parallelism, scrypt::Params::RECOMMENDED_LEN)
+    }
+
+    /// Derive a key of `length` bytes from a passphrase and a salt.
+    pub fn derive(&self, passphrase: &[u8], salt: &[u8], length: usize) -> Result<Secret> {
+        let invalid = |error: String| Error::InvalidArgument(format!("Cannot derive a key of {length} bytes: {error}"));
+
+        if length == 0 { return Err(invalid("at least one byte must be derived".into())); }
+
+        let mut key = Secret::from(vec![0_u8; length]);
+
+        match self.algorithm {
+            KdfAlgorithm::Argon2id => {
+                let params = self.argon2_params(Some(length)).map_err(|error| invalid(error.to_string()))?;
+
+                Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(passphrase, salt, &mut key)
+                    .map_err(|error| invalid(error.to_string()))?;
+            }
+            KdfAlgorithm::Scrypt => {
+                let params = self.scrypt_params().map_err(|error| invalid(error.to_string()))?;
+
+                scrypt::scrypt(passphrase, salt, &params, &mut key).map_err(|error| invalid(error.to_string()))?;
+            }
+            KdfAlgorithm::Pbkdf2 => pbkdf2::pbkdf2_hmac::<Sha256>(passphrase, salt, self.cost, &mut key),
+            KdfAlgorithm::Hkdf => {
+                Hkdf::<Sha256>::new(Some(salt), passphrase)
+                    .expand(&self.info, &mut key)
+                    .map_err(|_| invalid(format!("HKDF-SHA256 can derive at most {} bytes", 255 * 32)))?;
+            }
+        }
+
+        Ok(key)
+    }
+
+    /// Measure how long it takes to derive a key with these parameters.
+    fn measure(&self) -> Result<Duration> {
+        let start = Instant::now();
+
+        self.derive(CALIBRATION_INPUT, CALIBRATION_INPUT, 32)?;
+
+        Ok(start.elapsed())
+    }
+}
+
+impl Display for KdfSettings {
+    /// Write the parameters as the options that would choose them.
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = self.algorithm.to_possible_value().unwrap().get_name().to_owned();
+
+        match self.algorithm {
+            KdfAlgorithm::Argon2id => write!(formatter, "--algo {name} --cost {} --memory {} --parallelism {}", self.cost, self.memory, self.parallelism),
+            KdfAlgorithm::Scrypt => write!(formatter, "--algo {name} --cost {} --parallelism {}", self.cost, self.parallelism),
+            KdfAlgorithm::Pbkdf2 => write!(formatter, "--algo {name} --cost {}", self.cost),
+            KdfAlgorithm::Hkdf => write!(formatter, "--algo {name}")
+        }
+    }
+}
+
+/// Find the most expensive parameters that derive a key within `target` on this machine, returning them along with how long they took.
+///
+/// Argon2id is given as much memory as fits into the target, up to `memory` KiB, before the rest of the time is spent on extra passes,
+/// since memory is what makes it expensive to attack with dedicated hardware.
+pub fn calibrate(algorithm: KdfAlgorithm, target: Duration, memory: Option<u32>, parallelism: Option<u32>) -> Result<(KdfSettings, Duration)> {
+    let mut settings = KdfSettings::new(algorithm, None, memory, parallelism, None)?;
+
+    match algorithm {
+        KdfAlgorithm::Argon2id => {
+            let limit = memory.unwrap_or(CALIBRATION_MEMORY);
+
+            settings.cost = 1;
+            settings.memory = limit.min(8 * 1024).max(8 * settings.parallelism);
+
+            let mut elapsed = settings.measure()?;
+
+            while elapsed * 2 <= target && settings.memory.saturating_mul(2) <= limit {
+                settings.memory *= 2;
+                elapsed = settings.measure()?;
+            }
+
+            settings.cost = (target.as_secs_f64() / elapsed.as_secs_f64()).max(1.0) as u32;
+        }
+        KdfAlgorithm::Scrypt => {
+            settings.cost = 10;
+
+            while settings.measure()? * 2 <= target && settings.cost < CALIBRATION_LOG_N {
+                settings.cost += 1;
+            }
+        }
+        KdfAlgorithm::Pbkdf2 => {
+            settings.cost = CALIBRATION_ROUNDS;
+
+            let elapsed = settings.measure()?;
+
+            settings.cost = (CALIBRATION_ROUNDS as f64 * target.as_secs_f64() / elapsed.as_secs_f64()).clamp(1.0, u32::MAX as f64) as u32;
+        }
+        KdfAlgorithm::Hkdf => return Err(Error::InvalidArgument("HKDF has no cost to calibrate, since it is only meant for input that is already random".into()))
+    }
+
+    let elapsed = settings.measure()?;
+
+    Ok((settings, elapsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_pbkdf2_test_vector() {
+        // From RFC 7914, which lists PBKDF2-HMAC-SHA256 alongside scrypt.
+        let settings = KdfSettings::new(KdfAlgorithm::Pbkdf2, Some(1), None, None, None).unwrap();
+
+        assert_eq!(
+            hex::encode(settings.derive(b"passwd", b"salt", 64).unwrap()),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+        );
+    }
+
+    #[test]
+    fn matches_scrypt_test_vector() {
+        // From RFC 7914.
+        let settings = KdfSettings::new(KdfAlgorithm::Scrypt, Some(10), None, Some(16), None).unwrap();
+
+        assert_eq!(
+            hex::encode(settings.derive(b"password", b"NaCl", 64).unwrap()),
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+        );
+    }
+
+    #[test]
+    fn matches_hkdf_test_vector() {
+        // Test case 1 from RFC 5869.
+        let settings = KdfSettings::new(KdfAlgorithm::Hkdf, None, None, None, Some((0xF0..=0xF9).collect())).unwrap();
+        let salt = (0x00..=0x0C).collect::<Vec<u8>>();
+
+        assert_eq!(
+            hex::encode(settings.derive(&[0x0B; 22], &salt, 42).unwrap()),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+    }
+
+    #[test]
+    fn derives_argon2id_key_of_any_length() {
+        let settings = KdfSettings::new(KdfAlgorithm::Argon2id, Some(1), Some(64), None, None).unwrap();
+        let key = settings.derive(b"passphrase", b"saltsalt", 48).unwrap();
+
+        assert_eq!(key.len(), 48);
+        assert_eq!(settings.derive(b"passphrase", b"saltsalt", 48).unwrap(), key);
+        assert!(matches!(settings.derive(b"passphrase", b"salt", 48), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn rejects_parameters_that_do_not_apply() {
+        assert!(matches!(KdfSettings::new(KdfAlgorithm::Hkdf, Some(2), None, None, None), Err(Error::InvalidArgument(_))));
+        assert!(matches!(KdfSettings::new(KdfAlgorithm::Pbkdf2, None, None, Some(2), None), Err(Error::InvalidArgument(_))));
+        assert!(matches!(KdfSettings::new(KdfAlgorithm::Argon2id, None, None, None, Some(b"context".to_vec())), Err(Error::InvalidArgument(_))));
+        assert!(matches!(calibrate(KdfAlgorithm::Hkdf, Duration::from_millis(1), None, None), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn calibrates_to_cheapest_parameters_for_short_target() {
+        let (settings, _) = calibrate(KdfAlgorithm::Scrypt, Duration::from_nanos(1), None, None).unwrap();
+
+        assert_eq!(settings.cost, 10);
+        assert_eq!(settings.to_string(), "--algo scrypt --cost 10 --parallelism 1");
+    }
+}
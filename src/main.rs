@@ -1,48 +1,99 @@
 use std::fs::{read, File};
-use std::io::{stdin, stdout, Read, Write};
+use std::io::{copy, stderr, stdin, stdout, BufReader, IsTerminal, Write};
 use std::path::PathBuf;
-use std::sync::mpsc::channel;
-use std::thread::spawn;
+use std::process::ExitCode;
+use std::str::from_utf8;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::{spawn, JoinHandle};
 
+mod error;
+mod encoding;
 mod wordlist;
+mod checksum;
+mod pins;
+mod shuffle;
 mod config;
 mod analyze;
 mod generate;
 mod generators;
 mod visualize;
 mod panic;
+mod progress;
+mod output;
+mod random;
+mod shamir;
+mod hashing;
+mod yescrypt;
+mod kdf;
+mod secret;
+mod record;
+mod entropy;
 
-use config::{parse, setup_logging, Commands, GenerateCommands, UsernameCommands};
+use config::{parse, setup_logging, Arguments, Commands, GenerateCommands, HashOptions, NetworkCommands, OtpOptions, TokenCommands, UsernameCommands};
 use analyze::analyze;
-use generate::{create_base64, create_bytes, create_digits, create_hex, create_number, create_passphrase, create_password, create_username, UsernameKind};
-use rand::{thread_rng, prelude::SliceRandom};
+use generate::{CHANNEL_CAPACITY, create_base32, create_base58, create_base64, create_base85, create_bytes, create_crockford, create_z85, create_cards, create_coin, create_dice, create_digits, create_hex, create_ip, create_mac, create_ula, create_keypair, create_ksuid, create_nanoid, create_otp, create_token, create_ulid, create_uuid, create_number, create_passphrase, create_permutation, create_password, create_secure_pin, create_unique_number, create_username, UsernameKind};
+use encoding::Encoding;
+use error::{Error, Result};
+use log::{debug, error, info, warn};
+use rand::{prelude::SliceRandom, Rng};
+use generators::{complex_username_entropy, parse_number, simple_username_entropy, verify_token, Base64Alphabet, OtpKind, UuidVersion};
+use num_bigint::BigInt;
+use num_traits::One;
 use visualize::visualize;
 use wordlist::{load_default_wordlist, load_wordlist};
 use panic::setup_panic;
-
-type UnitResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
-type VecByteResult = Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+use pins::{effective_entropy, load_weak_pins};
+use shuffle::shuffle_lines;
+use output::{FramedWriter, Framing, SecureFile};
+use random::{read_entropy, RngSource, SETUP_STREAM};
+use hashing::HashSettings;
+use kdf::{calibrate, KdfSettings, SALT_LENGTH};
+use record::{Record, RecordFormat};
+use entropy::{minimum_length, uniform_entropy};
+use secret::Secret;
+use shamir::{combine_shares, split_secret, Share};
 
 /// Read data from a file or STDIN
-fn read_in(path: Option<PathBuf>) -> VecByteResult {
-    let mut buffer = Vec::<u8>::new();
+fn read_in(path: Option<PathBuf>) -> Result<Secret> {
+    let mut buffer = Secret::default();
 
     match path {
         Some(value) => {
-            buffer = read(value)?;
+            buffer = Secret::from(read(value)?);
         },
         None => {
-            stdin().read_to_end(&mut buffer)?;
+            copy(&mut stdin(), &mut buffer)?;
         }
     };
 
-    if buffer.is_empty() { return Err("There is no data to read".into()); }
+    if buffer.is_empty() { return Err(Error::InvalidArgument("There is no data to read".into())); }
 
     Ok(buffer)
 }
 
+/// Read a passphrase from STDIN, leaving out the line ending after it.
+fn read_passphrase() -> Result<Secret> {
+    let mut input = stdin();
+    let mut passphrase = Secret::default();
+
+    if input.is_terminal() {
+        writeln!(stderr(), "Type the passphrase, then press Enter and Ctrl-D to finish")?;
+    }
+
+    copy(&mut input, &mut passphrase)?;
+
+    let mut length = passphrase.len();
+
+    if passphrase[..length].ends_with(b"\n") { length -= 1; }
+    if passphrase[..length].ends_with(b"\r") { length -= 1; }
+
+    if length == 0 { return Err(Error::InvalidArgument("There is no passphrase to derive a key from".into())); }
+
+    Ok(Secret::from(&passphrase[..length]))
+}
+
 /// Loads the correct character set based on the available symbols
-fn load_character_set(numbers: bool, symbols: bool) -> Vec<char> {
+fn load_character_set<R: Rng>(numbers: bool, symbols: bool, rng: &mut R) -> Vec<char> {
     let flags = (numbers, symbols);
 
     let mut character_set: Vec<char> = match flags {
@@ -52,77 +103,446 @@ fn load_character_set(numbers: bool, symbols: bool) -> Vec<char> {
         (false, false) => "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().collect()
     };
 
-    let rng = &mut thread_rng();
-
     character_set.shuffle(rng);
 
     character_set
 }
 
-fn main() -> UnitResult {
-    let arguments = parse();
+/// Write every record from a generator to the output, in the order in which they are received.
+///
+/// Each record is a separate item, unless the generator streams one item in chunks.
+/// The entropy of the first record is printed on STDERR if `show_entropy` is set, since every record in a batch shares it.
+fn write_out<W: Write>(receiver: Receiver<Record>, mut output: FramedWriter<W>, format: Option<RecordFormat>, streamed: bool, show_entropy: bool) -> Result<()> {
+    let mut shown = !show_entropy;
 
-    setup_panic();
+    for record in receiver {
+        if !shown {
+            writeln!(stderr(), "Each secret has {:.2} bits of entropy", record.metadata.entropy)?;
 
-    setup_logging(&arguments.verbosity)?;
+            shown = true;
+        }
 
-    match arguments.command {
-        Commands::Generate { command } => {
-            let (sender, receiver) = channel::<Vec<u8>>();
-
-            let handle = match command {
-                GenerateCommands::Bytes { length }
-                    => spawn(move || create_bytes(sender, length)),
-                GenerateCommands::Hex { uppercase, length }
-                    => spawn(move || create_hex(sender, uppercase, length)),
-                GenerateCommands::Base64 { url_safe, length }
-                    => spawn(move || create_base64(sender, url_safe, length)),
-                GenerateCommands::Password { numbers, symbols, length, count }
+        match format {
+            Some(format) => output.write_item(&format.render(&record)?)?,
+            None if streamed => output.write_chunk(&record.secret)?,
+            None => output.write_item(&record.into_plain())?
+        }
+    }
+
+    output.finish()?;
+
+    Ok(())
+}
+
+/// Use the values given as arguments, or read them line by line from STDIN if there are none, ignoring blank lines.
+fn read_values(arguments: Vec<String>) -> Result<Vec<String>> {
+    let values = if arguments.is_empty() {
+        let buffer = read_in(None)?;
+
+        from_utf8(&buffer)
+            .map_err(|_| Error::InvalidArgument("The input is not valid UTF-8".into()))?
+            .lines()
+            .map(|line| line.to_owned())
+            .collect()
+    }
+    else { arguments };
+
+    let values = values
+        .iter()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_owned())
+        .collect();
+
+    Ok(values)
+}
+
+/// Start provisioning a one-time password secret, creating the QR code file first so that nothing is generated if it already exists.
+fn spawn_otp(sender: SyncSender<Record>, source: RngSource, kind: OtpKind, options: OtpOptions, force: bool) -> Result<JoinHandle<Result<()>>> {
+    let OtpOptions { issuer, account, algorithm, digits, qr_code } = options;
+    let qr_code = qr_code
+        .map(|path| SecureFile::create(&path, force))
+        .transpose()?;
+
+    Ok(spawn(move || create_otp(sender, &source, kind, issuer.as_deref(), &account, algorithm, digits, qr_code)))
+}
+
+/// Work out how long a secret should be, either from `--bits` or from the length that was given, which clap keeps from being given together.
+///
+/// `entropy` calculates the bits of entropy in a secret of a given length, with the same set sizes that are used to generate it.
+fn resolve_length(bits: Option<u32>, length: Option<usize>, entropy: impl Fn(usize) -> f64) -> Result<usize> {
+    match (bits, length) {
+        (Some(bits), _) => minimum_length(bits, entropy),
+        (None, Some(length)) => Ok(length),
+        (None, None) => Err(Error::InvalidArgument("Either give a length or --bits".into()))
+    }
+}
+
+/// Check the cost parameters of a hash before anything is generated, if a hash was asked for.
+fn load_hash_settings(options: HashOptions) -> Result<Option<HashSettings>> {
+    let HashOptions { algorithm, cost, memory, parallelism } = options;
+
+    algorithm
+        .map(|algorithm| HashSettings::new(algorithm, cost, memory, parallelism))
+        .transpose()
+}
+
+/// Start the generator that a sub-command of `generate` describes, sending its output to `sender`.
+fn spawn_generator(sender: SyncSender<Record>, source: RngSource, force: bool, command: GenerateCommands) -> Result<JoinHandle<Result<()>>> {
+    let handle = match command {
+        GenerateCommands::Bytes { progress, length }
+            => spawn(move || create_bytes(sender, &source, length.as_u64(), progress)),
+        GenerateCommands::Hex { uppercase, bits, length }
+            => {
+                let length = resolve_length(bits, length, |length| length as f64 * 8.0)?;
+
+                spawn(move || create_hex(sender, &source, uppercase, length))
+            },
+        GenerateCommands::Base64 { url_safe, alphabet, padding, wrap, bits, length }
+            => {
+                let length = resolve_length(bits, length, |length| length as f64 * 8.0)?;
+                let alphabet = if url_safe { Base64Alphabet::Url } else { alphabet };
+
+                let wrap = wrap.map(|width| width as usize);
+
+                spawn(move || create_base64(sender, &source, alphabet, padding, wrap, length))
+            },
+        GenerateCommands::Base32 { lowercase, padding, length }
+            => spawn(move || create_base32(sender, &source, lowercase, padding, length)),
+        GenerateCommands::Crockford { lowercase, length }
+            => spawn(move || create_crockford(sender, &source, lowercase, length)),
+        GenerateCommands::Base58 { length }
+            => spawn(move || create_base58(sender, &source, length)),
+        GenerateCommands::Z85 { length }
+            => spawn(move || create_z85(sender, &source, length)),
+        GenerateCommands::Base85 { length }
+            => spawn(move || create_base85(sender, &source, length)),
+        GenerateCommands::Keypair { kind, key_format }
+            => spawn(move || create_keypair(sender, &source, kind, key_format)),
+        GenerateCommands::Totp { options, period }
+            => spawn_otp(sender, source, OtpKind::Totp { period }, options, force)?,
+        GenerateCommands::Hotp { options, counter }
+            => spawn_otp(sender, source, OtpKind::Hotp { counter }, options, force)?,
+        GenerateCommands::Token { prefix, length, checksum, count }
+            => spawn(move || create_token(sender, &source, &prefix, length, checksum, count)),
+        GenerateCommands::Password { numbers, symbols, hashing, bits, length, count }
+            => {
+                let hashing = load_hash_settings(hashing)?;
+                let character_set = load_character_set(numbers, symbols, &mut source.stream(SETUP_STREAM));
+                let length = resolve_length(bits, length, |length| uniform_entropy(character_set.len(), length))?;
+
+                spawn(move || create_password(sender, &source, &character_set, length, hashing.as_ref(), count))
+            },
+        GenerateCommands::Passphrase { path, delimiter, separator, hashing, bits, length, count }
+            => {
+                let hashing = load_hash_settings(hashing)?;
+                let mut rng = source.stream(SETUP_STREAM);
+
+                let wordlist = match path {
+                    Some(path) => load_wordlist(&path, &delimiter, &mut rng)?,
+                    None => load_default_wordlist(&mut rng),
+                };
+                let length = resolve_length(bits, length, |length| uniform_entropy(wordlist.len(), length))?;
+
+                spawn(move || create_passphrase(sender, &source, &wordlist, &separator, length, hashing.as_ref(), count))
+            },
+        GenerateCommands::Username { capitalize, command }
+            => match command {
+                UsernameCommands::Simple { bits, length, count }
                     => {
-                        let character_set = load_character_set(numbers, symbols);
+                        let length = resolve_length(bits, length, simple_username_entropy)?;
 
-                        spawn(move || create_password(sender, &character_set, length, count))
+                        spawn(move || create_username(sender, &source, capitalize, UsernameKind::Simple, length, count))
                     },
-                GenerateCommands::Passphrase { path, delimiter, separator, length, count }
+                UsernameCommands::Complex { bits, length, count }
                     => {
-                        let mut rng = thread_rng();
+                        let length = resolve_length(bits, length, complex_username_entropy)?;
 
-                        let wordlist = match path {
-                            Some(path) => load_wordlist(&path, &delimiter, &mut rng)?,
-                            None => load_default_wordlist(&mut rng),
-                        };
+                        spawn(move || create_username(sender, &source, capitalize, UsernameKind::Complex, length, count))
+                    }
+            },
+        GenerateCommands::Network { command }
+            => match command {
+                NetworkCommands::Mac { style, uppercase, count }
+                    => spawn(move || create_mac(sender, &source, style, uppercase, count)),
+                NetworkCommands::Ula { count }
+                    => spawn(move || create_ula(sender, &source, count)),
+                NetworkCommands::Ip { cidr, count }
+                    => spawn(move || create_ip(sender, &source, &cidr, count))
+            },
+        GenerateCommands::Digits { secure_pin: true, top, bits, length, count, .. }
+            => {
+                // Every length has its own set of weak PINs, so the entropy that remains is only known once they are loaded.
+                let length = resolve_length(bits, length, |length| {
+                    if length < 4 { 0.0 } else { effective_entropy(length, load_weak_pins(length, top).len()) }
+                })?;
 
-                        spawn(move || create_passphrase(sender, &wordlist, &separator, length, count))
-                    },
-                GenerateCommands::Username { capitalize, command }
-                    => match command {
-                        UsernameCommands::Simple { length, count }
-                            => spawn(move || create_username(sender, capitalize, UsernameKind::Simple, length, count)),
-                        UsernameCommands::Complex { length, count }
-                            => spawn(move || create_username(sender, capitalize, UsernameKind::Complex, length, count))
-                    },
-                GenerateCommands::Digits { length, count }
-                    => spawn(move || create_digits(sender, length, count)),
-                GenerateCommands::Number { minimum, maximum, count }
-                    => spawn(move || create_number(sender, minimum, maximum, count))
+                if length < 4 { return Err(Error::InvalidArgument("Secure PINs must have at least four digits".into())); }
+
+                let weak_pins = load_weak_pins(length, top);
+                let entropy = effective_entropy(length, weak_pins.len());
+
+                info!("Excluded {} weak PINs, leaving {entropy:.2} bits of entropy per PIN", weak_pins.len());
+
+                spawn(move || create_secure_pin(sender, &source, &weak_pins, length, count))
+            },
+        GenerateCommands::Digits { checksum, prefix, bits, length, count, .. }
+            => {
+                let fixed = prefix.len() + checksum.map_or(0, |checksum| checksum.length());
+                let length = resolve_length(bits, length, |length| uniform_entropy(10, length.saturating_sub(fixed)))?;
+
+                spawn(move || create_digits(sender, &source, &prefix, checksum, length, count))
+            },
+        GenerateCommands::Number { precision, radix, step, unique, minimum, maximum, count }
+            => {
+                if precision > 0 && radix != 10 { return Err(Error::InvalidArgument("Decimal numbers can only be printed in base 10".into())); }
+
+                let minimum = parse_number(&minimum, precision)?;
+                let maximum = parse_number(&maximum, precision)?;
+                let step = match step {
+                    Some(step) => parse_number(&step, precision)?,
+                    None => BigInt::one()
+                };
+
+                if unique { spawn(move || create_unique_number(sender, &source, &minimum, &maximum, &step, precision, radix, count)) }
+                else { spawn(move || create_number(sender, &source, &minimum, &maximum, &step, precision, radix, count)) }
+            },
+        GenerateCommands::Uuid { version, monotonic, count }
+            => {
+                if monotonic && version != UuidVersion::V7 { return Err(Error::InvalidArgument("Only version 7 UUIDs can be monotonic".into())); }
+
+                spawn(move || create_uuid(sender, &source, version, monotonic, count))
+            }
+        GenerateCommands::Ulid { monotonic, count }
+            => spawn(move || create_ulid(sender, &source, monotonic, count)),
+        GenerateCommands::Nanoid { alphabet, size, count }
+            => {
+                let alphabet = alphabet.chars().collect::<Vec<char>>();
+
+                spawn(move || create_nanoid(sender, &source, &alphabet, size, count))
+            }
+        GenerateCommands::Ksuid { count }
+            => spawn(move || create_ksuid(sender, &source, count)),
+        GenerateCommands::Dice { notation, count }
+            => spawn(move || create_dice(sender, &source, &notation, count)),
+        GenerateCommands::Coin { count }
+            => spawn(move || create_coin(sender, &source, count)),
+        GenerateCommands::Cards { decks, draw }
+            => spawn(move || create_cards(sender, &source, decks, draw)),
+        GenerateCommands::Permutation { start, length }
+            => spawn(move || create_permutation(sender, &source, &start, length))
+    };
+
+    Ok(handle)
+}
+
+/// Run a generator and collect everything that it generates, rather than writing it out.
+fn generate_secret(source: RngSource, command: GenerateCommands) -> Result<Secret> {
+    let (sender, receiver) = sync_channel::<Record>(CHANNEL_CAPACITY);
+    let handle = spawn_generator(sender, source, false, command)?;
+    let mut secret = Secret::default();
+
+    let written = write_out(receiver, FramedWriter::new(&mut secret, Framing::lines(), false), None, false, false);
+    let generated = handle
+        .join()
+        .map_err(|_| Error::Internal("The generator stopped unexpectedly".into()))?;
+
+    written.and(generated)?;
+
+    Ok(secret)
+}
+
+fn run(arguments: Arguments) -> Result<()> {
+    setup_logging(&arguments.verbosity)?;
+
+    match arguments.command {
+        Commands::Generate { output, force, rng, insecure_seed, mix_entropy, output_delimiter, null, trailing_newline, show_entropy, output_format, command } => {
+            // Raw bytes are sent in chunks of a single item, which must not be separated.
+            let streamed = matches!(command, GenerateCommands::Bytes { .. });
+
+            if streamed && output_format.is_some() { return Err(Error::InvalidArgument("Raw bytes cannot be written as records, so encode them with a command such as hex instead".into())); }
+
+            // Create the file before generating anything, so that nothing is wasted if it already exists
+            let mut file = output
+                .map(|path| SecureFile::create(&path, force))
+                .transpose()?;
+
+            let entropy = mix_entropy.map(|path| read_entropy(&path)).transpose()?;
+
+            if let Some(entropy) = &entropy {
+                debug!("Mixed {} bytes with an estimated {:.1} bits of entropy into the seed", entropy.bytes.len(), entropy.bits);
+            }
+
+            let source = RngSource::new(rng, insecure_seed.as_deref(), entropy.as_ref().map(|entropy| &*entropy.bytes))?;
+
+            if insecure_seed.is_some() { warn!("The generator was seeded with --insecure-seed, so the output is reproducible and must not be used as a secret"); }
+
+            let framing = match output_format {
+                Some(format) => format.framing(),
+                None => {
+                    let delimiter = if null { vec![0] } else { output_delimiter.unwrap_or_else(|| b"\n".to_vec()) };
+
+                    Framing { delimiter, trailing: trailing_newline, ..Framing::default() }
+                }
             };
 
-            let mut stdout = stdout();
+            let (sender, receiver) = sync_channel::<Record>(CHANNEL_CAPACITY);
+
+            let handle = spawn_generator(sender, source, force, command)?;
+
+            let written = match file.as_mut() {
+                Some(file) => write_out(receiver, FramedWriter::new(file, framing, false), output_format, streamed, show_entropy),
+                None => write_out(receiver, FramedWriter::new(stdout(), framing, stdout().is_terminal()), output_format, streamed, show_entropy)
+            };
+            let generated = handle
+                .join()
+                .map_err(|_| Error::Internal("The generator stopped unexpectedly".into()))?;
+
+            written.and(generated)?;
+
+            if let Some(file) = file { file.commit()?; }
+        }
+        Commands::Split { threshold, shares, encoding, input, command } => {
+            if input.is_some() && command.is_some() { return Err(Error::InvalidArgument("Either read a secret or generate one, but not both".into())); }
+
+            let source = RngSource::new(None, None, None)?;
+            let secret = match command {
+                Some(command) => generate_secret(source.clone(), command)?,
+                None => read_in(input)?
+            };
+
+            let mut stdout = stdout().lock();
+
+            for share in split_secret(&secret, threshold, shares, &mut source.stream(0))? {
+                let mut share = share.encode(encoding);
 
-            for message in receiver {
-                stdout.write_all(&message)?;
+                share.push(b'\n');
+                stdout.write_all(&share)?;
             }
+        }
+        Commands::Combine { encoding, output, force, input } => {
+            // Create the file before reading any shares, so that none have to be typed in again if it already exists
+            let file = output
+                .map(|path| SecureFile::create(&path, force))
+                .transpose()?;
+            let shares = read_values(input)?
+                .iter()
+                .map(|share| Share::decode(share, encoding))
+                .collect::<Result<Vec<Share>>>()?;
+            let secret = combine_shares(&shares)?;
 
-            stdout.flush()?;
+            info!("Reconstructed the secret from {} shares", shares.len());
+
+            match file {
+                Some(mut file) => {
+                    file.write_all(&secret)?;
+                    file.commit()?;
+                }
+                None => stdout().write_all(&secret)?
+            }
+        }
+        Commands::Kdf { algorithm, memory, parallelism, calibrate: Some(target), .. } => {
+            let (settings, elapsed) = calibrate(algorithm, target, memory, parallelism)?;
+
+            info!("Deriving a key with these parameters took {} ms", elapsed.as_millis());
 
-            handle.join().unwrap();
+            writeln!(stdout(), "{settings}")?;
+        }
+        Commands::Kdf { algorithm, salt, info, cost, memory, parallelism, calibrate: None, encoding, output, force, length } => {
+            let settings = KdfSettings::new(algorithm, cost, memory, parallelism, info.map(String::into_bytes))?;
+
+            // Create the file before reading the passphrase, so that it doesn't have to be typed in again if the file already exists
+            let file = output
+                .map(|path| SecureFile::create(&path, force))
+                .transpose()?;
+
+            let salt = match salt {
+                Some(salt) => salt,
+                None => {
+                    let mut salt = vec![0_u8; SALT_LENGTH];
+
+                    RngSource::new(None, None, None)?.stream(0).fill(salt.as_mut_slice());
+
+                    // The salt is needed to derive the same key again, so it is written separately from the key.
+                    writeln!(stderr(), "{}", hex::encode(&salt))?;
+
+                    salt
+                }
+            };
+
+            let key = settings.derive(&read_passphrase()?, &salt, length)?;
+            let mut encoded = encoding.encode(&key)?;
+
+            if !matches!(encoding, Encoding::Raw) { encoded.push(b'\n'); }
+
+            match file {
+                Some(mut file) => {
+                    file.write_all(&encoded)?;
+                    file.commit()?;
+                }
+                None => stdout().write_all(&encoded)?
+            }
         }
         Commands::Analyze { input } => {
             let buffer = read_in(input)?;
 
-            let report = analyze(buffer);
+            let report = analyze(&buffer);
+
+            writeln!(stdout(), "{report}")?;
+        }
+        Commands::Validate { checksum, input } => {
+            let input = read_values(input)?;
+            let mut stdout = stdout().lock();
+            let mut invalid = 0;
+
+            for value in &input {
+                // Ignore the spaces and dashes that are commonly used to group digits.
+                let digits = value
+                    .bytes()
+                    .filter(|byte| !matches!(byte, b' ' | b'-'))
+                    .collect::<Vec<u8>>();
+                let valid = checksum.validate(&digits);
+
+                if !valid { invalid += 1; }
+
+                writeln!(stdout, "{value}\t{}", if valid { "valid" } else { "invalid" })?;
+            }
 
-            println!("{report}");
+            if invalid > 0 { return Err(Error::VerificationFailed(format!("{invalid} sequences of digits have invalid check digits"))); }
+        }
+        Commands::Token { command: TokenCommands::Verify { prefix, checksum, input } } => {
+            let input = read_values(input)?;
+            let mut stdout = stdout().lock();
+            let mut invalid = 0;
+
+            for token in &input {
+                let valid = verify_token(token, prefix.as_deref(), checksum);
+
+                if !valid { invalid += 1; }
+
+                writeln!(stdout, "{token}\t{}", if valid { "valid" } else { "invalid" })?;
+            }
+
+            if invalid > 0 { return Err(Error::VerificationFailed(format!("{invalid} tokens have invalid checksums"))); }
+        }
+        Commands::Shuffle { rng, insecure_seed, count, input } => {
+            let source = RngSource::new(rng, insecure_seed.as_deref(), None)?;
+
+            if insecure_seed.is_some() { warn!("The generator was seeded with --insecure-seed, so the order is reproducible and must not be relied on to be secret"); }
+
+            let rng = &mut source.stream(0);
+            let lines = match input {
+                Some(path) => shuffle_lines(BufReader::new(File::open(path)?), count, rng)?,
+                None => shuffle_lines(stdin().lock(), count, rng)?
+            };
+            let mut stdout = stdout();
+
+            for line in lines {
+                stdout.write_all(&line)?;
+                stdout.write_all(b"\n")?;
+            }
+
+            stdout.flush()?;
         }
         Commands::Visualize { input, output } => {
             let buffer = read_in(input)?;
@@ -146,3 +566,20 @@ fn main() -> UnitResult {
 
     Ok(())
 }
+
+fn main() -> ExitCode {
+    let arguments = parse();
+
+    setup_panic();
+
+    match run(arguments) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            // A closed pipe is the normal way for a reader such as `head` to say that it has seen enough.
+            if let Error::BrokenPipe = error { debug!("{error}"); }
+            else { error!("{error}"); }
+
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
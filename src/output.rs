@@ -0,0 +1,337 @@
+use std::fs::{remove_file, rename, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+use clap::ValueEnum;
+use log::debug;
+use rand::{thread_rng, Rng};
+
+use crate::error::{Error, Result};
+
+/// A file that secrets are written to.
+///
+/// The file is only readable and writable by its owner, and it is deleted again unless it is committed,
+/// so that a failure can never leave a partially-written secret behind.
+pub struct SecureFile {
+    file: File,
+    path: PathBuf,
+    /// When replacing an existing file, the temporary file that is renamed over it once it is complete.
+    temporary: Option<PathBuf>,
+    committed: bool
+}
+
+/// Open a new file that is only readable and writable by its owner, failing if anything already exists at the path.
+fn create_exclusive(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    options.open(path)
+}
+
+/// Choose an unpredictable path next to `path`, so that renaming it over `path` is atomic.
+fn temporary_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let suffix = thread_rng().gen::<u64>();
+
+    path.with_file_name(format!(".{name}.{suffix:016x}.tmp"))
+}
+
+/// Flush the directory that contains `path`, so that a rename survives a crash.
+#[cfg(unix)]
+fn sync_directory(path: &Path) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    };
+
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl SecureFile {
+    /// Create a file at `path`, refusing to replace an existing file unless `force` is set.
+    pub fn create(path: &Path, force: bool) -> Result<Self> {
+        if force {
+            let temporary = temporary_path(path);
+            let file = create_exclusive(&temporary)?;
+
+            debug!("Writing to the temporary file {temporary:?}");
+
+            return Ok(SecureFile { file, path: path.to_owned(), temporary: Some(temporary), committed: false });
+        }
+
+        let file = create_exclusive(path).map_err(|error| match error.kind() {
+            io::ErrorKind::AlreadyExists => Error::InvalidArgument(format!("{path:?} already exists, so use --force to replace it")),
+            _ => error.into()
+        })?;
+
+        Ok(SecureFile { file, path: path.to_owned(), temporary: None, committed: false })
+    }
+
+    /// Flush the file to stable storage and move it to its final path.
+    pub fn commit(mut self) -> Result<()> {
+        self.file.flush()?;
+        self.file.sync_all()?;
+
+        if let Some(temporary) = &self.temporary {
+            rename(temporary, &self.path)?;
+        }
+
+        sync_directory(&self.path)?;
+
+        self.committed = true;
+
+        debug!("Wrote {:?}", self.path);
+
+        Ok(())
+    }
+}
+
+impl Write for SecureFile {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.file.write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for SecureFile {
+    fn drop(&mut self) {
+        if self.committed { return; }
+
+        let path = self.temporary.as_ref().unwrap_or(&self.path);
+
+        if remove_file(path).is_ok() { debug!("Deleted the incomplete file {path:?}"); }
+    }
+}
+
+/// When to write a delimiter after the last item, as well as between items.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TrailingNewline {
+    Always,
+    Never,
+    /// Only when writing to a terminal, and only if the output doesn't already end with the delimiter, so that the prompt starts on a new line.
+    #[default]
+    Auto
+}
+
+/// How the items that a generator sends are separated from each other when they are written out.
+pub struct Framing {
+    /// Written before the first item, such as the header row of a CSV file.
+    pub header: Vec<u8>,
+    pub delimiter: Vec<u8>,
+    pub trailing: TrailingNewline,
+    /// Written after the last item and any delimiter that follows it.
+    pub footer: Vec<u8>,
+    /// Written instead of everything else when there are no items at all, such as an empty JSON array.
+    pub empty: Vec<u8>
+}
+
+impl Framing {
+    /// Separate items with line feeds without ending on one, which is how a generated secret is collected before it is split into shares.
+    pub fn lines() -> Self {
+        Framing { trailing: TrailingNewline::Never, ..Framing::default() }
+    }
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing { header: Vec::new(), delimiter: b"\n".to_vec(), trailing: TrailingNewline::Auto, footer: Vec::new(), empty: Vec::new() }
+    }
+}
+
+/// Writes items with a delimiter between each of them, deciding whether to end with one too once every item has been written.
+pub struct FramedWriter<W: Write> {
+    output: W,
+    framing: Framing,
+    terminal: bool,
+    started: bool,
+    /// Whether the last bytes written were the delimiter, which `Auto` relies on.
+    delimited: bool
+}
+
+impl<W: Write> FramedWriter<W> {
+    /// Wrap `output`, where `terminal` says whether it is a terminal.
+    pub fn new(output: W, framing: Framing, terminal: bool) -> Self {
+        FramedWriter { output, framing, terminal, started: false, delimited: false }
+    }
+
+    /// Write a new item, after a delimiter unless it is the first.
+    pub fn write_item(&mut self, item: &[u8]) -> io::Result<()> {
+        if self.started { self.output.write_all(&self.framing.delimiter)?; }
+
+        self.write_chunk(item)
+    }
+
+    /// Write more of the current item, such as the next chunk of a stream of bytes, after the header if nothing has been written yet.
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        if !self.started { self.output.write_all(&self.framing.header)?; }
+
+        self.output.write_all(chunk)?;
+
+        self.started = true;
+        self.delimited = !self.framing.delimiter.is_empty() && chunk.ends_with(&self.framing.delimiter);
+
+        Ok(())
+    }
+
+    /// End the output with a delimiter if the framing asks for one, or write what stands for no items if there were none, then flush it.
+    pub fn finish(mut self) -> io::Result<W> {
+        let trailing = match self.framing.trailing {
+            TrailingNewline::Always => true,
+            TrailingNewline::Never => false,
+            TrailingNewline::Auto => self.terminal && !self.delimited
+        };
+
+        if !self.started { self.output.write_all(&self.framing.empty)?; }
+        else {
+            if trailing { self.output.write_all(&self.framing.delimiter)?; }
+
+            self.output.write_all(&self.framing.footer)?;
+        }
+
+        self.output.flush()?;
+
+        Ok(self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{read, write};
+
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        let suffix = thread_rng().gen::<u64>();
+
+        temp_dir().join(format!("ciphergen-{name}-{suffix:016x}"))
+    }
+
+    #[test]
+    fn writes_new_file() {
+        let path = test_path("new");
+        let mut file = SecureFile::create(&path, false).unwrap();
+
+        file.write_all(b"secret").unwrap();
+        file.commit().unwrap();
+
+        assert_eq!(read(&path).unwrap(), b"secret");
+
+        remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restricts_permissions_to_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = test_path("permissions");
+
+        SecureFile::create(&path, false).unwrap().commit().unwrap();
+
+        let mode = path.metadata().unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_replace_existing_file() {
+        let path = test_path("existing");
+
+        write(&path, b"original").unwrap();
+
+        assert!(matches!(SecureFile::create(&path, false), Err(Error::InvalidArgument(_))));
+        assert_eq!(read(&path).unwrap(), b"original");
+
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replaces_existing_file_when_forced() {
+        let path = test_path("forced");
+
+        write(&path, b"original").unwrap();
+
+        let mut file = SecureFile::create(&path, true).unwrap();
+
+        file.write_all(b"secret").unwrap();
+
+        assert_eq!(read(&path).unwrap(), b"original");
+
+        file.commit().unwrap();
+
+        assert_eq!(read(&path).unwrap(), b"secret");
+
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn deletes_uncommitted_file() {
+        let path = test_path("uncommitted");
+        let mut file = SecureFile::create(&path, false).unwrap();
+
+        file.write_all(b"secret").unwrap();
+
+        drop(file);
+
+        assert!(!path.exists());
+    }
+
+    fn frame(delimiter: &[u8], trailing: TrailingNewline, terminal: bool, items: &[&[u8]]) -> Vec<u8> {
+        let framing = Framing { delimiter: delimiter.to_vec(), trailing, ..Framing::default() };
+        let mut writer = FramedWriter::new(Vec::new(), framing, terminal);
+
+        for item in items { writer.write_item(item).unwrap(); }
+
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn separates_items_with_delimiter() {
+        assert_eq!(frame(b"\0", TrailingNewline::Never, false, &[b"a", b"b", b"c"]), b"a\0b\0c");
+        assert_eq!(frame(b", ", TrailingNewline::Always, false, &[b"a", b"b"]), b"a, b, ");
+    }
+
+    #[test]
+    fn ends_with_delimiter_only_on_terminal() {
+        assert_eq!(frame(b"\n", TrailingNewline::Auto, true, &[b"a", b"b"]), b"a\nb\n");
+        assert_eq!(frame(b"\n", TrailingNewline::Auto, false, &[b"a", b"b"]), b"a\nb");
+        assert_eq!(frame(b"\n", TrailingNewline::Auto, true, &[b"-----END KEY-----\n"]), b"-----END KEY-----\n");
+    }
+
+    #[test]
+    fn joins_chunks_of_one_item() {
+        let framing = Framing { trailing: TrailingNewline::Always, ..Framing::default() };
+        let mut writer = FramedWriter::new(Vec::new(), framing, false);
+
+        writer.write_chunk(b"ab").unwrap();
+        writer.write_chunk(b"cd").unwrap();
+
+        assert_eq!(writer.finish().unwrap(), b"abcd\n");
+    }
+
+    #[test]
+    fn frames_empty_output() {
+        let framing = Framing { header: b"[".to_vec(), footer: b"]".to_vec(), empty: b"[]\n".to_vec(), ..Framing::default() };
+
+        assert_eq!(FramedWriter::new(Vec::new(), framing, false).finish().unwrap(), b"[]\n");
+        assert_eq!(frame(b"\n", TrailingNewline::Always, true, &[]), b"");
+    }
+}
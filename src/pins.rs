@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use log::debug;
+
+/// Days in each month, allowing for leap years.
+const DAYS_IN_MONTH: [u32; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// The range of years that are treated as likely birth years or anniversaries.
+const YEARS: std::ops::RangeInclusive<u32> = 1900..=2099;
+
+/// The length of the longest pattern that is checked for repetition.
+const MAXIMUM_PATTERN_LENGTH: usize = 4;
+
+/// Add every PIN that repeats a shorter pattern, such as `0000`, `1212` or `123123`.
+fn add_repeats(pins: &mut HashSet<Vec<u8>>, length: usize) {
+    for unit in (1..=MAXIMUM_PATTERN_LENGTH.min(length / 2)).filter(|unit| length.is_multiple_of(*unit)) {
+        for value in 0..10_u32.pow(unit as u32) {
+            let pattern = format!("{value:0unit$}");
+
+            pins.insert(pattern.repeat(length / unit).into_bytes());
+        }
+    }
+}
+
+/// Add every PIN whose digits ascend or descend by one, such as `1234`, `7890` or `4321`.
+fn add_runs(pins: &mut HashSet<Vec<u8>>, length: usize) {
+    for start in 0..10 {
+        let ascending = (0..length).map(|index| b'0' + ((start + index) % 10) as u8).collect::<Vec<u8>>();
+        let descending = (0..length).map(|index| b'0' + ((start + 10 * length - index) % 10) as u8).collect::<Vec<u8>>();
+
+        pins.insert(ascending);
+        pins.insert(descending);
+    }
+}
+
+/// Add every PIN that can be read as a calendar date in a common layout.
+fn add_dates(pins: &mut HashSet<Vec<u8>>, length: usize) {
+    let days = DAYS_IN_MONTH
+        .iter()
+        .enumerate()
+        .flat_map(|(month, days)| (1..=*days).map(move |day| (month as u32 + 1, day)));
+
+    for (month, day) in days {
+        match length {
+            4 => {
+                pins.insert(format!("{month:02}{day:02}").into_bytes());
+                pins.insert(format!("{day:02}{month:02}").into_bytes());
+            },
+            6 => for year in 0..100 {
+                pins.insert(format!("{day:02}{month:02}{year:02}").into_bytes());
+                pins.insert(format!("{month:02}{day:02}{year:02}").into_bytes());
+                pins.insert(format!("{year:02}{month:02}{day:02}").into_bytes());
+            },
+            8 => for year in YEARS {
+                pins.insert(format!("{day:02}{month:02}{year}").into_bytes());
+                pins.insert(format!("{month:02}{day:02}{year}").into_bytes());
+                pins.insert(format!("{year}{month:02}{day:02}").into_bytes());
+            },
+            _ => {}
+        }
+    }
+
+    if length == 4 {
+        for year in YEARS { pins.insert(year.to_string().into_bytes()); }
+    }
+}
+
+/// Load the set of PINs of the given length that are too predictable to be issued.
+///
+/// This includes the `top` most common PINs from the bundled frequency table,
+/// as well as repeated patterns, ascending and descending runs, and dates.
+pub fn load_weak_pins(length: usize, top: usize) -> HashSet<Vec<u8>> {
+    let mut pins = include_str!("./pins.txt")
+        .split('\n')
+        .filter(|value| value.len() == length)
+        .take(top)
+        .map(|value| value.as_bytes().to_vec())
+        .collect::<HashSet<_>>();
+
+    add_repeats(&mut pins, length);
+    add_runs(&mut pins, length);
+    add_dates(&mut pins, length);
+
+    debug!("Excluded {} weak PINs of length {length}", pins.len());
+
+    pins
+}
+
+/// Calculate the entropy, in bits, of a PIN chosen uniformly from those that are not excluded.
+pub fn effective_entropy(length: usize, excluded: usize) -> f64 {
+    let total = 10_f64.powi(length as i32);
+
+    (total - excluded as f64).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_common_pins() {
+        let pins = load_weak_pins(4, usize::MAX);
+
+        for pin in ["1234", "0000", "1212", "2580", "1990", "0229", "3112", "7890", "9876"] {
+            assert!(pins.contains(pin.as_bytes()), "expected {pin} to be excluded");
+        }
+    }
+
+    #[test]
+    fn allows_uncommon_pins() {
+        let pins = load_weak_pins(4, usize::MAX);
+
+        for pin in ["8351", "4796", "3872"] {
+            assert!(!pins.contains(pin.as_bytes()), "expected {pin} to be allowed");
+        }
+    }
+
+    #[test]
+    fn excludes_six_digit_dates() {
+        let pins = load_weak_pins(6, usize::MAX);
+
+        assert!(pins.contains(b"311299".as_slice()));
+        assert!(pins.contains(b"123123".as_slice()));
+    }
+
+    #[test]
+    fn calculates_effective_entropy() {
+        assert_eq!(effective_entropy(4, 0), 10000_f64.log2());
+        assert_eq!(effective_entropy(4, 5000), 5000_f64.log2());
+    }
+}
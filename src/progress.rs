@@ -0,0 +1,54 @@
+use std::io::{stderr, Write};
+use std::time::{Duration, Instant};
+
+use bytesize::ByteSize;
+
+/// How often the progress indicator is redrawn.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// A progress indicator for long-running outputs, which is drawn on STDERR so that it never mixes with the output itself.
+pub struct Progress {
+    enabled: bool,
+    total: u64,
+    current: u64,
+    started: Instant,
+    drawn: Option<Instant>
+}
+
+impl Progress {
+    pub fn new(enabled: bool, total: u64) -> Self {
+        Progress { enabled, total, current: 0, started: Instant::now(), drawn: None }
+    }
+
+    /// Record that another `amount` bytes have been produced, redrawing the indicator if enough time has passed.
+    pub fn advance(&mut self, amount: u64) {
+        self.current += amount;
+
+        let stale = self.drawn.is_none_or(|drawn| drawn.elapsed() >= INTERVAL);
+
+        if self.enabled && stale { self.draw(); }
+    }
+
+    /// Draw the indicator one last time and move the cursor to the next line.
+    pub fn finish(&mut self) {
+        if !self.enabled { return; }
+
+        self.draw();
+
+        writeln!(stderr()).ok();
+    }
+
+    fn draw(&mut self) {
+        let percentage = if self.total == 0 { 100.0 } else { self.current as f64 / self.total as f64 * 100.0 };
+        let seconds = self.started.elapsed().as_secs_f64();
+        let rate = if seconds > 0.0 { (self.current as f64 / seconds) as u64 } else { 0 };
+
+        let mut stderr = stderr();
+
+        // A failure to draw the indicator should never interrupt the output.
+        write!(stderr, "\r{} / {} ({percentage:.1}%) at {}/s", ByteSize(self.current), ByteSize(self.total), ByteSize(rate)).ok();
+        stderr.flush().ok();
+
+        self.drawn = Some(Instant::now());
+    }
+}
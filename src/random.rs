@@ -0,0 +1,374 @@
+use std::fs::File;
+use std::io::{copy, stdin, stderr, BufRead, IsTerminal, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use clap::ValueEnum;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::analyze::shannon_entropy;
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+
+/// The stream used for work that happens before generation starts, such as shuffling a character set.
+///
+/// Items are numbered from zero, so this can never collide with the stream of an item.
+pub const SETUP_STREAM: u64 = u64::MAX;
+
+/// The largest number of bytes that HMAC_DRBG may produce in a single request, as SP 800-90A allows.
+const MAXIMUM_REQUEST: usize = 1 << 16;
+
+/// A label that separates the HMAC_DRBG instances of this program from any others instantiated with the same seed.
+const PERSONALIZATION: &[u8] = b"ciphergen";
+
+/// The largest number of bytes that are read from a file or device of extra entropy, which is far more than a 256-bit seed can hold.
+const MIXING_LIMIT: u64 = 4096;
+
+/// A label that separates seeds with extra entropy mixed in from any other use of the same hash.
+const MIXING_LABEL: &[u8] = b"ciphergen entropy mixing";
+
+/// The random number generators that secrets can be drawn from.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RngKind {
+    /// The operating system's random number generator
+    Os,
+    /// A ChaCha20 stream cipher keyed by the operating system, with a separate stream for every item
+    Chacha20,
+    /// An HMAC-SHA256 DRBG from NIST SP 800-90A, seeded by the operating system
+    HmacDrbg
+}
+
+/// A deterministic random bit generator built on HMAC-SHA256, as described by NIST SP 800-90A.
+///
+/// Reseeding is never needed, since no instance lives long enough to approach the reseed interval.
+/// Its state determines everything it will ever generate, so it is wiped when it is dropped.
+pub struct HmacDrbg {
+    key: Zeroizing<[u8; 32]>,
+    value: Zeroizing<[u8; 32]>
+}
+
+impl HmacDrbg {
+    pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Self {
+        let mut drbg = HmacDrbg { key: Zeroizing::new([0x00; 32]), value: Zeroizing::new([0x01; 32]) };
+
+        drbg.update(&[entropy, nonce, personalization]);
+
+        drbg
+    }
+
+    fn hmac<'a>(key: &[u8; 32], parts: impl IntoIterator<Item = &'a [u8]>) -> [u8; 32] {
+        // HMAC accepts keys of any length, so this can never fail.
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+
+        for part in parts { mac.update(part); }
+
+        mac.finalize().into_bytes().into()
+    }
+
+    fn update(&mut self, provided: &[&[u8]]) {
+        for separator in [[0x00], [0x01]] {
+            // The parts are fed to the HMAC one after another, so they are never copied into a buffer of their own.
+            let parts = [self.value.as_slice(), &separator].into_iter().chain(provided.iter().copied());
+
+            *self.key = Self::hmac(&self.key, parts);
+            *self.value = Self::hmac(&self.key, [self.value.as_slice()]);
+
+            // The second round is skipped when there is no provided data.
+            if provided.iter().all(|part| part.is_empty()) { break; }
+        }
+    }
+
+    fn generate(&mut self, output: &mut [u8]) {
+        for chunk in output.chunks_mut(32) {
+            *self.value = Self::hmac(&self.key, [self.value.as_slice()]);
+
+            chunk.copy_from_slice(&self.value[..chunk.len()]);
+        }
+
+        self.update(&[]);
+    }
+}
+
+impl RngCore for HmacDrbg {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0_u8; 4];
+
+        self.fill_bytes(&mut bytes);
+
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0_u8; 8];
+
+        self.fill_bytes(&mut bytes);
+
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, destination: &mut [u8]) {
+        for request in destination.chunks_mut(MAXIMUM_REQUEST) {
+            self.generate(request);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, destination: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.fill_bytes(destination);
+
+        Ok(())
+    }
+}
+
+impl CryptoRng for HmacDrbg {}
+
+/// One stream of random numbers from the selected generator.
+pub enum Random {
+    Os(OsRng),
+    ChaCha20(Box<ChaCha20Rng>),
+    HmacDrbg(HmacDrbg)
+}
+
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            Random::Os(rng) => rng.next_u32(),
+            Random::ChaCha20(rng) => rng.next_u32(),
+            Random::HmacDrbg(rng) => rng.next_u32()
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            Random::Os(rng) => rng.next_u64(),
+            Random::ChaCha20(rng) => rng.next_u64(),
+            Random::HmacDrbg(rng) => rng.next_u64()
+        }
+    }
+
+    fn fill_bytes(&mut self, destination: &mut [u8]) {
+        match self {
+            Random::Os(rng) => rng.fill_bytes(destination),
+            Random::ChaCha20(rng) => rng.fill_bytes(destination),
+            Random::HmacDrbg(rng) => rng.fill_bytes(destination)
+        }
+    }
+
+    fn try_fill_bytes(&mut self, destination: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        match self {
+            Random::Os(rng) => rng.try_fill_bytes(destination),
+            Random::ChaCha20(rng) => rng.try_fill_bytes(destination),
+            Random::HmacDrbg(rng) => rng.try_fill_bytes(destination)
+        }
+    }
+}
+
+impl CryptoRng for Random {}
+
+impl Drop for Random {
+    /// Wipe the state of a ChaCha20 stream, which would otherwise reveal everything it has yet to generate.
+    fn drop(&mut self) {
+        if let Random::ChaCha20(rng) = self {
+            // SAFETY: ChaCha20Rng is made only of integers and arrays of them, for which all zero bytes are a valid value, and it has no destructor that could observe them.
+            unsafe { zeroize::zeroize_flat_type(&mut **rng as *mut ChaCha20Rng) }
+        }
+    }
+}
+
+/// The generator that every stream is derived from.
+///
+/// Every item gets its own stream, which is derived from a single seed and the index of the item,
+/// so that the output for a given seed never depends on how the work is split between threads.
+/// The seed determines every secret, so it is wiped when the source and any clones of it are dropped.
+#[derive(Clone)]
+pub struct RngSource {
+    kind: RngKind,
+    seed: Zeroizing<[u8; 32]>
+}
+
+impl RngSource {
+    /// Choose a generator, seeding it from the operating system unless an insecure seed is given.
+    ///
+    /// Extra entropy is hashed into the seed along with the operating system's randomness, so it can only ever add to it.
+    /// Either an insecure seed or extra entropy selects ChaCha20 by default, since the operating system's generator cannot be seeded.
+    pub fn new(kind: Option<RngKind>, insecure_seed: Option<&[u8]>, entropy: Option<&[u8]>) -> Result<Self> {
+        let seeded = insecure_seed.is_some() || entropy.is_some();
+        let kind = kind.unwrap_or(if seeded { RngKind::Chacha20 } else { RngKind::Os });
+
+        if kind == RngKind::Os && seeded {
+            return Err(Error::InvalidArgument("The OS generator cannot be seeded, so choose --rng chacha20 or --rng hmac-drbg instead".into()));
+        }
+
+        let seed = match insecure_seed {
+            Some(seed) => Zeroizing::new(Sha256::digest(seed).into()),
+            None => {
+                let mut seed = Zeroizing::new([0_u8; 32]);
+
+                OsRng.fill_bytes(seed.as_mut_slice());
+
+                match entropy {
+                    Some(entropy) => mix(&seed, entropy),
+                    None => seed
+                }
+            }
+        };
+
+        Ok(RngSource { kind, seed })
+    }
+
+    /// Derive the stream of random numbers for the item at `index`.
+    pub fn stream(&self, index: u64) -> Random {
+        match self.kind {
+            RngKind::Os => Random::Os(OsRng),
+            RngKind::Chacha20 => {
+                let mut rng = ChaCha20Rng::from_seed(*self.seed);
+
+                rng.set_stream(index);
+
+                Random::ChaCha20(Box::new(rng))
+            }
+            RngKind::HmacDrbg => Random::HmacDrbg(HmacDrbg::new(self.seed.as_slice(), &index.to_be_bytes(), PERSONALIZATION))
+        }
+    }
+}
+
+/// Extra entropy supplied by the user, to be mixed into the seed.
+pub struct Entropy {
+    pub bytes: Secret,
+    /// A rough estimate of the number of bits of entropy, which ignores the timing of typed input.
+    pub bits: f64
+}
+
+/// Estimate the entropy of some bytes from the frequency of each byte.
+fn estimate_bits(bytes: &[u8]) -> f64 {
+    shannon_entropy(bytes) * bytes.len() as f64
+}
+
+/// Hash extra entropy together with a seed from the operating system.
+fn mix(seed: &[u8; 32], entropy: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mixed = Sha256::new()
+        .chain_update(MIXING_LABEL)
+        .chain_update(seed)
+        .chain_update(entropy)
+        .finalize();
+
+    Zeroizing::new(mixed.into())
+}
+
+/// Read extra entropy from a file or device, or from lines typed on STDIN if the path is `-`.
+///
+/// Only the first few kilobytes are read, so that endless devices such as `/dev/hwrng` can be used.
+pub fn read_entropy(path: &Path) -> Result<Entropy> {
+    let entropy = if path == Path::new("-") { read_typed_entropy()? }
+    else {
+        let mut bytes = Secret::with_capacity(MIXING_LIMIT as usize);
+
+        copy(&mut File::open(path)?.take(MIXING_LIMIT), &mut bytes)?;
+
+        Entropy { bits: estimate_bits(&bytes), bytes }
+    };
+
+    if entropy.bytes.is_empty() { return Err(Error::InvalidArgument("There is no entropy to mix in".into())); }
+
+    Ok(entropy)
+}
+
+/// Read lines such as dice rolls or random key presses from STDIN, along with the time at which each one arrived.
+fn read_typed_entropy() -> Result<Entropy> {
+    let input = stdin();
+    let start = Instant::now();
+    let mut typed = Secret::default();
+    let mut timings = Secret::default();
+
+    if input.is_terminal() {
+        writeln!(stderr(), "Type dice rolls or random keys, pressing Enter after each line, then press Ctrl-D to finish")?;
+    }
+
+    for line in input.lock().lines() {
+        let line = Zeroizing::new(line?);
+
+        typed.extend_from_slice(line.trim().as_bytes());
+        timings.extend_from_slice(&start.elapsed().as_nanos().to_le_bytes());
+    }
+
+    let bits = estimate_bits(&typed);
+
+    let mut bytes = Secret::with_capacity(typed.len() + timings.len());
+
+    bytes.extend_from_slice(&typed);
+    bytes.extend_from_slice(&timings);
+
+    Ok(Entropy { bytes, bits })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(source: &RngSource, index: u64) -> [u8; 32] {
+        let mut bytes = [0_u8; 32];
+
+        source.stream(index).fill_bytes(&mut bytes);
+
+        bytes
+    }
+
+    #[test]
+    fn matches_hmac_drbg_reference() {
+        let entropy = (0..32).collect::<Vec<u8>>();
+        let mut drbg = HmacDrbg::new(&entropy, b"nonce", b"");
+        let mut bytes = [0_u8; 32];
+
+        drbg.fill_bytes(&mut bytes);
+
+        // Calculated independently with Python's hmac module.
+        assert_eq!(hex::encode(bytes), "3678347238fe931ca116e1698f1b17775628e1b242a8822440f48c4e21857242");
+    }
+
+    #[test]
+    fn seeded_streams_are_reproducible() {
+        for kind in [RngKind::Chacha20, RngKind::HmacDrbg] {
+            let first = RngSource::new(Some(kind), Some(b"fixture"), None).unwrap();
+            let second = RngSource::new(Some(kind), Some(b"fixture"), None).unwrap();
+
+            assert_eq!(sample(&first, 7), sample(&second, 7));
+            assert_ne!(sample(&first, 7), sample(&first, 8));
+        }
+    }
+
+    #[test]
+    fn unseeded_streams_differ() {
+        let first = RngSource::new(Some(RngKind::Chacha20), None, None).unwrap();
+        let second = RngSource::new(Some(RngKind::Chacha20), None, None).unwrap();
+
+        assert_ne!(sample(&first, 0), sample(&second, 0));
+    }
+
+    #[test]
+    fn rejects_seeding_os_generator() {
+        let result = RngSource::new(Some(RngKind::Os), Some(b"fixture"), None);
+
+        assert!(matches!(result, Err(Error::InvalidArgument(_))))
+    }
+
+    #[test]
+    fn mixed_entropy_changes_seed() {
+        let seed = [0x2A_u8; 32];
+
+        assert_ne!(*mix(&seed, b"31415"), seed);
+        assert_ne!(mix(&seed, b"31415"), mix(&seed, b"31416"));
+        assert_eq!(mix(&seed, b"31415"), mix(&seed, b"31415"));
+    }
+
+    #[test]
+    fn mixing_entropy_selects_seedable_generator() {
+        let source = RngSource::new(None, None, Some(b"31415")).unwrap();
+
+        assert!(source.kind == RngKind::Chacha20);
+        assert!(matches!(RngSource::new(Some(RngKind::Os), None, Some(b"31415")), Err(Error::InvalidArgument(_))));
+    }
+}
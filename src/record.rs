@@ -0,0 +1,337 @@
+use std::str::from_utf8;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use num_bigint::BigInt;
+
+use crate::error::{Error, Result};
+use crate::output::{Framing, TrailingNewline};
+use crate::secret::Secret;
+
+/// The columns of a CSV or TSV file of records, in order.
+const COLUMNS: [&str; 6] = ["secret", "hash", "type", "parameters", "alphabet_size", "entropy_bits"];
+
+/// The structured formats that records can be written in.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RecordFormat {
+    /// A single JSON array of records
+    Json,
+    /// One JSON record per line
+    Ndjson,
+    /// Comma-separated values, with quotes around fields that need them, after a header row
+    Csv,
+    /// Tab-separated values, with tabs, line breaks and backslashes escaped, after a header row
+    Tsv
+}
+
+/// The value of a parameter that a secret was generated with.
+pub enum Value {
+    Text(String),
+    Number(u64),
+    Flag(bool)
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Number(value as u64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Number(value as u64)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Flag(value)
+    }
+}
+
+/// Describes how every secret in a batch was generated.
+pub struct Metadata {
+    /// The name of the generator, which is usually the name of its sub-command.
+    pub kind: &'static str,
+    pub parameters: Vec<(&'static str, Value)>,
+    /// The number of characters, words or values that each symbol of a secret is drawn from, if they all come from the same set.
+    pub alphabet: Option<BigInt>,
+    /// How many bits of entropy each secret contains, or the first of a draw without replacement contains.
+    pub entropy: f64
+}
+
+impl Metadata {
+    pub fn new(kind: &'static str, entropy: f64) -> Self {
+        Metadata { kind, parameters: Vec::new(), alphabet: None, entropy }
+    }
+
+    pub fn parameter(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.parameters.push((name, value.into()));
+
+        self
+    }
+
+    pub fn alphabet(mut self, size: impl Into<BigInt>) -> Self {
+        self.alphabet = Some(size.into());
+
+        self
+    }
+
+    /// Share the metadata between every secret in a batch.
+    pub fn shared(self) -> Arc<Metadata> {
+        Arc::new(self)
+    }
+}
+
+/// A generated secret, along with everything that is known about how it was generated.
+pub struct Record {
+    pub secret: Secret,
+    /// The hash of the secret, if one was asked for.
+    pub hash: Option<String>,
+    pub metadata: Arc<Metadata>
+}
+
+impl Record {
+    pub fn new(secret: Secret, metadata: &Arc<Metadata>) -> Self {
+        Record { secret, hash: None, metadata: Arc::clone(metadata) }
+    }
+
+    /// The secret on its own, followed by a tab and its hash if it has one.
+    pub fn into_plain(self) -> Secret {
+        let Some(hash) = self.hash else { return self.secret };
+        let mut secret = self.secret;
+
+        secret.push(b'\t');
+        secret.extend_from_slice(hash.as_bytes());
+
+        secret
+    }
+}
+
+/// Round a number of bits to two decimal places, which is more precision than an estimate of entropy deserves.
+///
+/// There is nothing to write for a degenerate estimate that isn't a finite number, which JSON has no way to represent.
+fn format_bits(bits: f64) -> Option<String> {
+    bits.is_finite().then(|| format!("{bits:.2}"))
+}
+
+/// Write `text` as a quoted JSON string.
+fn push_json_string(output: &mut Secret, text: &str) {
+    let mut buffer = [0_u8; 4];
+
+    output.push(b'"');
+
+    for character in text.chars() {
+        match character {
+            '"' => output.extend_from_slice(b"\\\""),
+            '\\' => output.extend_from_slice(b"\\\\"),
+            '\n' => output.extend_from_slice(b"\\n"),
+            '\r' => output.extend_from_slice(b"\\r"),
+            '\t' => output.extend_from_slice(b"\\t"),
+            character if character < ' ' => output.extend_from_slice(format!("\\u{:04x}", character as u32).as_bytes()),
+            character => output.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes())
+        }
+    }
+
+    output.push(b'"');
+}
+
+/// Write the parameters of a batch as a JSON object.
+fn push_json_parameters(output: &mut Secret, parameters: &[(&str, Value)]) {
+    output.push(b'{');
+
+    for (index, (name, value)) in parameters.iter().enumerate() {
+        if index > 0 { output.push(b','); }
+
+        push_json_string(output, name);
+        output.push(b':');
+
+        match value {
+            Value::Text(text) => push_json_string(output, text),
+            Value::Number(number) => output.extend_from_slice(number.to_string().as_bytes()),
+            Value::Flag(flag) => output.extend_from_slice(flag.to_string().as_bytes())
+        }
+    }
+
+    output.push(b'}');
+}
+
+/// Write a field of a CSV file, quoting it as RFC 4180 describes if it contains a separator, a quote or a line break.
+fn push_csv_field(output: &mut Secret, field: &str) {
+    if !field.contains([',', '"', '\r', '\n']) {
+        output.extend_from_slice(field.as_bytes());
+
+        return;
+    }
+
+    output.push(b'"');
+
+    for byte in field.bytes() {
+        if byte == b'"' { output.push(b'"'); }
+
+        output.push(byte);
+    }
+
+    output.push(b'"');
+}
+
+/// Write a field of a TSV file, escaping the characters that would otherwise break it into several fields or rows.
+fn push_tsv_field(output: &mut Secret, field: &str) {
+    for byte in field.bytes() {
+        match byte {
+            b'\\' => output.extend_from_slice(b"\\\\"),
+            b'\t' => output.extend_from_slice(b"\\t"),
+            b'\n' => output.extend_from_slice(b"\\n"),
+            b'\r' => output.extend_from_slice(b"\\r"),
+            byte => output.push(byte)
+        }
+    }
+}
+
+impl RecordFormat {
+    /// How records in this format are separated, and what comes before and after them.
+    pub fn framing(self) -> Framing {
+        let header = |separator: &str| format!("{}\n", COLUMNS.join(separator)).into_bytes();
+
+        match self {
+            RecordFormat::Json => Framing { header: b"[\n  ".to_vec(), delimiter: b",\n  ".to_vec(), trailing: TrailingNewline::Never, footer: b"\n]\n".to_vec(), empty: b"[]\n".to_vec() },
+            RecordFormat::Ndjson => Framing { delimiter: b"\n".to_vec(), trailing: TrailingNewline::Always, ..Framing::default() },
+            RecordFormat::Csv => Framing { header: header(","), delimiter: b"\n".to_vec(), trailing: TrailingNewline::Always, empty: header(","), ..Framing::default() },
+            RecordFormat::Tsv => Framing { header: header("\t"), delimiter: b"\n".to_vec(), trailing: TrailingNewline::Always, empty: header("\t"), ..Framing::default() }
+        }
+    }
+
+    /// Render a record in this format, without the delimiter that follows it.
+    pub fn render(self, record: &Record) -> Result<Secret> {
+        let secret = from_utf8(&record.secret).map_err(|_| Error::InvalidArgument("Only secrets made of text can be written as records".into()))?;
+        let metadata = &record.metadata;
+        let alphabet = metadata.alphabet.as_ref().map(BigInt::to_string);
+        let entropy = format_bits(metadata.entropy);
+        let mut output = Secret::with_capacity(secret.len() + 256);
+
+        match self {
+            RecordFormat::Json | RecordFormat::Ndjson => {
+                output.extend_from_slice(b"{\"secret\":");
+                push_json_string(&mut output, secret);
+                output.extend_from_slice(b",\"hash\":");
+
+                match &record.hash {
+                    Some(hash) => push_json_string(&mut output, hash),
+                    None => output.extend_from_slice(b"null")
+                }
+
+                output.extend_from_slice(b",\"type\":");
+                push_json_string(&mut output, metadata.kind);
+                output.extend_from_slice(b",\"parameters\":");
+                push_json_parameters(&mut output, &metadata.parameters);
+                output.extend_from_slice(b",\"alphabet_size\":");
+                output.extend_from_slice(alphabet.as_deref().unwrap_or("null").as_bytes());
+                output.extend_from_slice(b",\"entropy_bits\":");
+                output.extend_from_slice(entropy.as_deref().unwrap_or("null").as_bytes());
+                output.push(b'}');
+            }
+            RecordFormat::Csv | RecordFormat::Tsv => {
+                let (separator, push_field): (u8, fn(&mut Secret, &str)) = match self {
+                    RecordFormat::Csv => (b',', push_csv_field),
+                    _ => (b'\t', push_tsv_field)
+                };
+                let mut parameters = Secret::default();
+
+                push_json_parameters(&mut parameters, &metadata.parameters);
+
+                let parameters = from_utf8(&parameters).map_err(|error| Error::Internal(error.to_string()))?;
+                let fields = [secret, record.hash.as_deref().unwrap_or_default(), metadata.kind, parameters, alphabet.as_deref().unwrap_or_default(), entropy.as_deref().unwrap_or_default()];
+
+                for (index, field) in fields.into_iter().enumerate() {
+                    if index > 0 { output.push(separator); }
+
+                    push_field(&mut output, field);
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// The name that a value of a `ValueEnum` is given on the command line.
+pub fn value_name<T: ValueEnum>(value: &T) -> String {
+    value.to_possible_value().map(|value| value.get_name().to_owned()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::FramedWriter;
+
+    use super::*;
+
+    fn record(secret: &str, hash: Option<&str>) -> Record {
+        let metadata = Metadata::new("password", 95.27)
+            .parameter("length", 16_usize)
+            .parameter("separator", "\"-\"")
+            .alphabet(62)
+            .shared();
+        let mut record = Record::new(Secret::from(secret.as_bytes()), &metadata);
+
+        record.hash = hash.map(str::to_owned);
+
+        record
+    }
+
+    #[test]
+    fn renders_json() {
+        let rendered = RecordFormat::Json.render(&record("a\"b\\c\n", None)).unwrap();
+
+        assert_eq!(rendered, r#"{"secret":"a\"b\\c\n","hash":null,"type":"password","parameters":{"length":16,"separator":"\"-\""},"alphabet_size":62,"entropy_bits":95.27}"#);
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        let rendered = RecordFormat::Csv.render(&record("a,b", Some("$2b$12$x"))).unwrap();
+
+        assert_eq!(rendered, r#""a,b",$2b$12$x,password,"{""length"":16,""separator"":""\""-\""""}",62,95.27"#);
+    }
+
+    #[test]
+    fn escapes_tsv_fields() {
+        let rendered = RecordFormat::Tsv.render(&record("a\tb\\", None)).unwrap();
+
+        assert_eq!(rendered, "a\\tb\\\\\t\tpassword\t{\"length\":16,\"separator\":\"\\\\\"-\\\\\"\"}\t62\t95.27");
+    }
+
+    #[test]
+    fn frames_empty_batch() {
+        let empty = |format: RecordFormat| FramedWriter::new(Vec::new(), format.framing(), false).finish().unwrap();
+
+        assert_eq!(empty(RecordFormat::Json), b"[]\n");
+        assert_eq!(empty(RecordFormat::Ndjson), b"");
+        assert_eq!(empty(RecordFormat::Csv), b"secret,hash,type,parameters,alphabet_size,entropy_bits\n");
+    }
+
+    #[test]
+    fn leaves_out_infinite_entropy() {
+        let metadata = Metadata::new("number", f64::NEG_INFINITY).alphabet(0).shared();
+        let record = Record::new(Secret::from(b"0".as_slice()), &metadata);
+
+        assert_eq!(RecordFormat::Json.render(&record).unwrap(), r#"{"secret":"0","hash":null,"type":"number","parameters":{},"alphabet_size":0,"entropy_bits":null}"#);
+        assert_eq!(RecordFormat::Csv.render(&record).unwrap(), "0,,number,{},0,");
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+
+use zeroize::Zeroizing;
+
+/// The bytes of a secret, which are wiped from memory when they are dropped.
+///
+/// A `Vec` frees its old allocation without wiping it whenever it grows, so a secret only ever grows by moving into a new buffer and wiping the old one.
+#[derive(Clone, Default)]
+pub struct Secret(Zeroizing<Vec<u8>>);
+
+impl Secret {
+    /// Create an empty secret that can hold `capacity` bytes before it has to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Secret(Zeroizing::new(Vec::with_capacity(capacity)))
+    }
+
+    /// Make sure that `additional` more bytes fit without growing.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.0.capacity() - self.0.len() >= additional { return; }
+
+        let capacity = (self.0.len() + additional).max(self.0.capacity() * 2);
+        let mut grown = Secret::with_capacity(capacity);
+
+        grown.0.extend_from_slice(&self.0);
+
+        // Replacing the old buffer drops it, which wipes it.
+        *self = grown;
+    }
+
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.reserve(bytes.len());
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        self.extend_from_slice(&[byte]);
+    }
+}
+
+impl From<Vec<u8>> for Secret {
+    /// Take ownership of the bytes without copying them.
+    fn from(bytes: Vec<u8>) -> Self {
+        Secret(Zeroizing::new(bytes))
+    }
+}
+
+impl From<String> for Secret {
+    /// Take ownership of the string without copying it.
+    fn from(string: String) -> Self {
+        Secret::from(string.into_bytes())
+    }
+}
+
+impl From<&[u8]> for Secret {
+    fn from(bytes: &[u8]) -> Self {
+        let mut secret = Secret::with_capacity(bytes.len());
+
+        secret.extend_from_slice(bytes);
+
+        secret
+    }
+}
+
+impl FromIterator<u8> for Secret {
+    fn from_iter<I: IntoIterator<Item = u8>>(iterator: I) -> Self {
+        let iterator = iterator.into_iter();
+        let mut secret = Secret::with_capacity(iterator.size_hint().0);
+
+        for byte in iterator { secret.push(byte); }
+
+        secret
+    }
+}
+
+impl FromIterator<char> for Secret {
+    /// Encode the characters as UTF-8.
+    fn from_iter<I: IntoIterator<Item = char>>(iterator: I) -> Self {
+        let iterator = iterator.into_iter();
+        let mut secret = Secret::with_capacity(iterator.size_hint().0);
+        let mut buffer = Zeroizing::new([0_u8; 4]);
+
+        for character in iterator {
+            secret.extend_from_slice(character.encode_utf8(buffer.as_mut_slice()).as_bytes());
+        }
+
+        secret
+    }
+}
+
+impl<'a> FromIterator<&'a char> for Secret {
+    fn from_iter<I: IntoIterator<Item = &'a char>>(iterator: I) -> Self {
+        iterator.into_iter().copied().collect()
+    }
+}
+
+impl Deref for Secret {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DerefMut for Secret {
+    /// Change the bytes in place, which can never reallocate them.
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for Secret {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> PartialEq<T> for Secret {
+    fn eq(&self, other: &T) -> bool {
+        self.0.as_slice() == other.as_ref()
+    }
+}
+
+impl Eq for Secret {}
+
+impl Hash for Secret {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_slice().hash(state);
+    }
+}
+
+impl Debug for Secret {
+    /// Never print the secret itself, in case it ends up in a log.
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Secret({} bytes)", self.0.len())
+    }
+}
+
+impl Write for Secret {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.extend_from_slice(buffer);
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_without_losing_bytes() {
+        let mut secret = Secret::with_capacity(1);
+
+        for byte in 0..=255 { secret.push(byte); }
+
+        secret.extend_from_slice(b"end");
+
+        assert_eq!(secret.len(), 259);
+        assert_eq!(secret[..256], (0..=255).collect::<Vec<u8>>());
+        assert!(secret.ends_with(b"end"));
+    }
+
+    #[test]
+    fn collects_characters_as_utf8() {
+        let secret = "pässwörd".chars().collect::<Secret>();
+
+        assert_eq!(secret, "pässwörd");
+    }
+
+    #[test]
+    fn redacts_debug_output() {
+        assert_eq!(format!("{:?}", Secret::from(b"hunter2".as_slice())), "Secret(7 bytes)");
+    }
+}
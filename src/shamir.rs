@@ -0,0 +1,328 @@
+use std::collections::{BTreeMap, HashMap};
+
+use clap::ValueEnum;
+use crc32fast::hash;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::encoding::{decode_base32, encode_base32, from_radix, to_radix, BASE32};
+use crate::error::{Error, Result};
+use crate::secret::Secret;
+use crate::wordlist::default_words;
+
+/// The version of the share format, which is also the first byte of every share so that it never begins with a zero byte.
+const VERSION: u8 = 1;
+
+/// The number of bytes before the data of a share: the version, the group, the threshold and the ID.
+const HEADER_LENGTH: usize = 7;
+
+/// The number of bytes of the CRC-32 checksum at the end of every share.
+const CHECKSUM_LENGTH: usize = 4;
+
+/// The number of bytes of a SHA-256 digest of the secret that are split along with it, so that a wrong reconstruction can be detected.
+const DIGEST_LENGTH: usize = 4;
+
+/// The ways in which a share can be written down.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ShareEncoding {
+    /// Lowercase hexadecimal
+    Hex,
+    /// Unpadded RFC 4648 Base32
+    Base32,
+    /// Words from the bundled wordlist, separated by spaces
+    Words
+}
+
+/// One share of a secret, which reveals nothing about the secret without enough other shares from the same group.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Share {
+    /// A random identifier that is shared by every share of the same secret.
+    pub group: [u8; 4],
+    /// The number of shares that are needed to reconstruct the secret.
+    pub threshold: u8,
+    /// The x coordinate of the share, from 1 to 255.
+    pub id: u8,
+    data: Zeroizing<Vec<u8>>
+}
+
+impl Share {
+    fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut bytes = Zeroizing::new(Vec::with_capacity(HEADER_LENGTH + self.data.len() + CHECKSUM_LENGTH));
+
+        bytes.extend([VERSION]);
+        bytes.extend(self.group);
+        bytes.extend([self.threshold, self.id]);
+        bytes.extend(self.data.iter());
+
+        let checksum = hash(&bytes);
+
+        bytes.extend(checksum.to_be_bytes());
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        // Every share carries at least one byte of the secret as well as its digest.
+        if bytes.len() <= HEADER_LENGTH + DIGEST_LENGTH + CHECKSUM_LENGTH { return Err(Error::InvalidArgument("The share is too short".into())); }
+        if bytes[0] != VERSION { return Err(Error::InvalidArgument(format!("Version {} shares are not supported", bytes[0]))); }
+
+        let (payload, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LENGTH);
+
+        if hash(payload).to_be_bytes() != checksum {
+            return Err(Error::VerificationFailed("The share is corrupted, since its checksum does not match".into()));
+        }
+
+        let share = Share {
+            group: payload[1..5].try_into().unwrap(),
+            threshold: payload[5],
+            id: payload[6],
+            data: Zeroizing::new(payload[HEADER_LENGTH..].to_vec())
+        };
+
+        Ok(share)
+    }
+
+    /// Write the share down as text.
+    pub fn encode(&self, encoding: ShareEncoding) -> Secret {
+        let bytes = self.to_bytes();
+
+        match encoding {
+            ShareEncoding::Hex => Secret::from(hex::encode(bytes)),
+            ShareEncoding::Base32 => encode_base32(&bytes, BASE32, false),
+            ShareEncoding::Words => {
+                let words = default_words();
+                let mut text = Secret::default();
+
+                for (index, digit) in to_radix(&bytes, words.len() as u32).iter().enumerate() {
+                    if index > 0 { text.push(b' '); }
+
+                    text.extend_from_slice(words[*digit as usize].as_bytes());
+                }
+
+                text
+            }
+        }
+    }
+
+    /// Read a share that was written down as text, checking that it has not been corrupted.
+    pub fn decode(text: &str, encoding: ShareEncoding) -> Result<Self> {
+        let invalid = || Error::InvalidArgument(format!("{text} is not a valid share"));
+
+        let bytes = Zeroizing::new(match encoding {
+            ShareEncoding::Hex => hex::decode(text.trim()).map_err(|_| invalid())?,
+            ShareEncoding::Base32 => decode_base32(&text.trim().to_ascii_uppercase(), BASE32).ok_or_else(invalid)?,
+            ShareEncoding::Words => {
+                let words = default_words();
+                let indices = words
+                    .iter()
+                    .enumerate()
+                    .map(|(index, word)| (*word, index as u32))
+                    .collect::<HashMap<_, _>>();
+                let digits = text
+                    .split_whitespace()
+                    .map(|word| indices.get(word.to_lowercase().as_str()).copied().ok_or_else(invalid))
+                    .collect::<Result<Vec<u32>>>()?;
+
+                from_radix(&digits, words.len() as u32).ok_or_else(invalid)?
+            }
+        });
+
+        Share::from_bytes(&bytes)
+    }
+}
+
+/// Multiply two elements of GF(2^8), using the same reducing polynomial as AES.
+///
+/// No branches or table lookups depend on the operands, so the time taken does not reveal the secret.
+fn multiply(mut left: u8, mut right: u8) -> u8 {
+    let mut product = 0;
+
+    for _ in 0..8 {
+        product ^= left & (right & 1).wrapping_neg();
+
+        let carry = left >> 7;
+
+        left = (left << 1) ^ (0x1B & carry.wrapping_neg());
+        right >>= 1;
+    }
+
+    product
+}
+
+/// Find the multiplicative inverse of a non-zero element of GF(2^8), which is that element raised to the power of 254.
+fn invert(value: u8) -> u8 {
+    let mut result = 1;
+    let mut base = value;
+    let mut exponent = 254;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 { result = multiply(result, base); }
+
+        base = multiply(base, base);
+        exponent >>= 1;
+    }
+
+    result
+}
+
+/// Split a secret into `count` shares, any `threshold` of which are enough to reconstruct it.
+pub fn split_secret<R: Rng>(secret: &[u8], threshold: u8, count: u8, rng: &mut R) -> Result<Vec<Share>> {
+    if secret.is_empty() { return Err(Error::InvalidArgument("There is no secret to split".into())); }
+    if threshold < 2 { return Err(Error::InvalidArgument("At least two shares must be needed to reconstruct the secret".into())); }
+    if threshold > count { return Err(Error::PolicyInfeasible(format!("Cannot require {threshold} shares when only {count} are created"))); }
+
+    let digest = Sha256::digest(secret);
+    let secret = Zeroizing::new([secret, &digest[..DIGEST_LENGTH]].concat());
+    let mut group = [0_u8; 4];
+    let mut coefficients = Zeroizing::new(vec![0_u8; threshold as usize]);
+
+    rng.fill_bytes(&mut group);
+
+    let mut shares = (1..=count)
+        .map(|id| Share { group, threshold, id, data: Zeroizing::new(Vec::with_capacity(secret.len())) })
+        .collect::<Vec<_>>();
+
+    // Every byte of the secret is the constant term of its own random polynomial of degree `threshold - 1`.
+    for &byte in secret.iter() {
+        coefficients[0] = byte;
+
+        rng.fill_bytes(&mut coefficients[1..]);
+
+        for share in shares.iter_mut() {
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(0, |value, coefficient| multiply(value, share.id) ^ coefficient);
+
+            share.data.push(value);
+        }
+    }
+
+    Ok(shares)
+}
+
+/// Reconstruct a secret from at least as many shares as its threshold.
+pub fn combine_shares(shares: &[Share]) -> Result<Secret> {
+    let first = shares.first().ok_or_else(|| Error::InvalidArgument("There are no shares to combine".into()))?;
+    let mut unique = BTreeMap::<u8, &Share>::new();
+
+    for share in shares {
+        if share.group != first.group || share.threshold != first.threshold || share.data.len() != first.data.len() {
+            return Err(Error::VerificationFailed("The shares belong to different secrets".into()));
+        }
+
+        if let Some(existing) = unique.insert(share.id, share) {
+            if existing != share { return Err(Error::VerificationFailed(format!("There are two different shares with the ID {}", share.id))); }
+        }
+    }
+
+    let threshold = first.threshold as usize;
+
+    if unique.len() < threshold {
+        return Err(Error::InvalidArgument(format!("{} different shares were given, but {threshold} are needed", unique.len())));
+    }
+
+    let chosen = unique.values().take(threshold).collect::<Vec<_>>();
+
+    // The Lagrange basis polynomial of each share, evaluated at zero.
+    let weights = chosen
+        .iter()
+        .map(|share| {
+            chosen
+                .iter()
+                .filter(|other| other.id != share.id)
+                .fold(1, |weight, other| multiply(weight, multiply(other.id, invert(other.id ^ share.id))))
+        })
+        .collect::<Vec<u8>>();
+
+    let secret = (0..first.data.len())
+        .map(|index| {
+            chosen
+                .iter()
+                .zip(&weights)
+                .fold(0, |value, (share, weight)| value ^ multiply(share.data[index], *weight))
+        })
+        .collect::<Secret>();
+
+    let (secret, digest) = secret.split_at(secret.len() - DIGEST_LENGTH);
+
+    if Sha256::digest(secret)[..DIGEST_LENGTH] != *digest {
+        return Err(Error::VerificationFailed("The shares do not reconstruct the original secret".into()));
+    }
+
+    Ok(Secret::from(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn multiplies_in_aes_field() {
+        assert_eq!(multiply(0x57, 0x83), 0xC1);
+        assert_eq!(multiply(0x53, 0xCA), 0x01);
+        assert_eq!(invert(0x53), 0xCA);
+    }
+
+    #[test]
+    fn combines_any_threshold_of_shares() {
+        let secret = b"correct horse battery staple";
+        let shares = split_secret(secret, 3, 5, &mut thread_rng()).unwrap();
+
+        for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1]] {
+            let chosen = subset.map(|index| shares[index].clone());
+
+            assert_eq!(combine_shares(&chosen).unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn rejects_too_few_shares() {
+        let shares = split_secret(b"secret", 3, 5, &mut thread_rng()).unwrap();
+        let duplicated = [shares[0].clone(), shares[1].clone(), shares[1].clone()];
+
+        assert!(matches!(combine_shares(&duplicated), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn rejects_mismatched_shares() {
+        let first = split_secret(b"secret", 2, 3, &mut thread_rng()).unwrap();
+        let second = split_secret(b"secret", 2, 3, &mut thread_rng()).unwrap();
+
+        assert!(matches!(combine_shares(&[first[0].clone(), second[1].clone()]), Err(Error::VerificationFailed(_))));
+    }
+
+    #[test]
+    fn encodes_and_decodes_shares() {
+        let shares = split_secret(&[0, 0, 0, 1], 2, 2, &mut thread_rng()).unwrap();
+
+        for encoding in [ShareEncoding::Hex, ShareEncoding::Base32, ShareEncoding::Words] {
+            for share in &shares {
+                assert_eq!(&Share::decode(from_utf8(&share.encode(encoding)).unwrap(), encoding).unwrap(), share);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_shares_without_secret() {
+        let share = Share { group: [1, 2, 3, 4], threshold: 2, id: 1, data: Zeroizing::new(vec![0; DIGEST_LENGTH]) };
+
+        assert!(matches!(Share::decode(from_utf8(&share.encode(ShareEncoding::Hex)).unwrap(), ShareEncoding::Hex), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn detects_corrupted_shares() {
+        let shares = split_secret(b"secret", 2, 3, &mut thread_rng()).unwrap();
+        let mut encoded = shares[0].encode(ShareEncoding::Hex);
+        let last = encoded.len() - 1;
+
+        encoded[last] = if encoded[last] == b'0' { b'1' } else { b'0' };
+
+        assert!(matches!(Share::decode(from_utf8(&encoded).unwrap(), ShareEncoding::Hex), Err(Error::VerificationFailed(_))));
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{BufRead, Result};
+
+use log::debug;
+use rand::{seq::SliceRandom, Rng};
+
+/// Read the next line from a reader, without its line ending.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut line = Vec::<u8>::new();
+
+    if reader.read_until(b'\n', &mut line)? == 0 { return Ok(None); }
+
+    if line.ends_with(b"\n") { line.pop(); }
+    if line.ends_with(b"\r") { line.pop(); }
+
+    Ok(Some(line))
+}
+
+/// Read every line from a reader and return them in a random order.
+///
+/// If `count` is given, only that many lines are selected by reservoir sampling,
+/// so that the memory required is proportional to `count` rather than to the size of the input.
+pub fn shuffle_lines<R: BufRead, G: Rng>(mut reader: R, count: Option<usize>, rng: &mut G) -> Result<Vec<Vec<u8>>> {
+    let mut lines = Vec::<Vec<u8>>::new();
+    let mut seen = 0_usize;
+
+    while let Some(line) = read_line(&mut reader)? {
+        match count {
+            Some(count) if seen >= count => {
+                let index = rng.gen_range(0..=seen);
+
+                if index < count { lines[index] = line; }
+            },
+            _ => lines.push(line)
+        }
+
+        seen += 1;
+    }
+
+    // The reservoir holds a uniform selection of lines, but not in a uniform order.
+    lines.shuffle(rng);
+
+    debug!("Selected {} of {seen} lines", lines.len());
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::io::Cursor;
+    use std::str::from_utf8;
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    fn numbered_lines(count: usize) -> Cursor<Vec<u8>> {
+        let input = (0..count)
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Cursor::new(input.into_bytes())
+    }
+
+    #[test]
+    fn shuffles_every_line() {
+        let lines = shuffle_lines(numbered_lines(1000), None, &mut thread_rng()).unwrap();
+        let mut values = lines
+            .iter()
+            .map(|line| from_utf8(line).unwrap().parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+
+        values.sort();
+
+        assert_eq!(values, (0..1000).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn samples_distinct_lines() {
+        let lines = shuffle_lines(numbered_lines(1000), Some(10), &mut thread_rng()).unwrap();
+        let unique = lines.iter().collect::<HashSet<_>>();
+
+        assert_eq!(lines.len(), 10);
+        assert_eq!(unique.len(), 10);
+    }
+
+    #[test]
+    fn samples_fewer_lines_than_requested() {
+        let lines = shuffle_lines(numbered_lines(5), Some(10), &mut thread_rng()).unwrap();
+
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn strips_line_endings() {
+        let lines = shuffle_lines(Cursor::new(b"a\r\n".to_vec()), None, &mut thread_rng()).unwrap();
+
+        assert_eq!(lines, vec![b"a".to_vec()]);
+    }
+}
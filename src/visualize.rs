@@ -3,9 +3,16 @@ use std::io::Write;
 use bytesize::ByteSize;
 use log::debug;
 use png::{BitDepth, ColorType, Compression, Encoder};
+use qrcode::{Color, QrCode};
+use zeroize::Zeroizing;
 
-type BoxedError<'a> = Box<dyn std::error::Error + Send + Sync + 'a>;
-type UnitResult<'a> = Result<(), BoxedError<'a>>;
+use crate::error::{Error, Result};
+
+/// The number of pixels along each side of a module in a QR code.
+const QR_SCALE: usize = 8;
+
+/// The number of light modules around a QR code, which scanners need to find it.
+const QR_QUIET_ZONE: usize = 4;
 
 /// Render the input data as a bitmap, substituting zeros for missing bytes.
 fn draw_bitmap(data: &[u8], width: usize, height: usize) -> Vec<u8> {
@@ -24,7 +31,31 @@ fn draw_bitmap(data: &[u8], width: usize, height: usize) -> Vec<u8> {
     bitmap
 }
 
-pub fn visualize<'a, W: Write>(writer: W, data: &[u8]) -> UnitResult<'a> {
+/// Render a QR code as a bitmap, with a light border around it.
+///
+/// The bitmap shows whatever the code encodes, so it is wiped when it is dropped.
+fn draw_qr_code(code: &QrCode) -> (usize, Zeroizing<Vec<u8>>) {
+    let modules = code.width();
+    let size = (modules + QR_QUIET_ZONE * 2) * QR_SCALE;
+    let mut bitmap = Zeroizing::new(vec![255; size * size]);
+
+    for y in 0..modules {
+        for x in 0..modules {
+            if code[(x, y)] == Color::Light { continue; }
+
+            for row in 0..QR_SCALE {
+                let start = (y + QR_QUIET_ZONE) * QR_SCALE + row;
+                let offset = start * size + (x + QR_QUIET_ZONE) * QR_SCALE;
+
+                bitmap[offset..offset + QR_SCALE].fill(0);
+            }
+        }
+    }
+
+    (size, bitmap)
+}
+
+pub fn visualize<W: Write>(writer: W, data: &[u8]) -> Result<()> {
     let size = data.len() as u64;
     let resolution = (size as f64).sqrt().ceil();
     let (width, height) = (resolution as u32, resolution as u32);
@@ -45,3 +76,24 @@ pub fn visualize<'a, W: Write>(writer: W, data: &[u8]) -> UnitResult<'a> {
 
     Ok(())
 }
+
+/// Encode text as a QR code, and write it as a PNG image.
+pub fn render_qr_code<W: Write>(writer: W, data: &[u8]) -> Result<()> {
+    let code = QrCode::new(data).map_err(|error| Error::InvalidArgument(format!("Cannot encode a QR code: {error}")))?;
+    let (size, bitmap) = draw_qr_code(&code);
+
+    debug!("\nModules: {0}x{0}\nResolution: {size}x{size}", code.width());
+
+    let mut encoder = Encoder::new(writer, size as u32, size as u32);
+
+    encoder.set_color(ColorType::Grayscale);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_compression(Compression::Best);
+
+    let mut writer = encoder.write_header()?;
+
+    writer.write_image_data(&bitmap)?;
+    writer.finish()?;
+
+    Ok(())
+}
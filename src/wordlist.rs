@@ -3,10 +3,9 @@ use std::{fs::read_to_string, path::PathBuf};
 use log::debug;
 use rand::{seq::SliceRandom, Rng};
 
-type BoxedError<'a> = Box<dyn std::error::Error + Send + Sync + 'a>;
-type StringVecResult<'a> = Result<Vec<String>, BoxedError<'a>>;
+use crate::error::{Error, Result};
 
-pub fn load_wordlist<'a, R: Rng + Sized>(path: &PathBuf, delimiter: &str, rng: &mut R) -> StringVecResult<'a> {
+pub fn load_wordlist<R: Rng + Sized>(path: &PathBuf, delimiter: &str, rng: &mut R) -> Result<Vec<String>> {
     let input = read_to_string(path)?;
     let mut wordlist = input
         .split(delimiter)
@@ -17,18 +16,25 @@ pub fn load_wordlist<'a, R: Rng + Sized>(path: &PathBuf, delimiter: &str, rng: &
 
     wordlist.shuffle(rng);
 
-    if wordlist.is_empty() { return Err(format!("The wordlist at {path:?} contains no words").into()); }
+    if wordlist.is_empty() { return Err(Error::EmptyAlphabet(format!("The wordlist at {path:?} contains no words"))); }
 
     debug!("Loaded {count} words from the wordlist at {path:?}");
 
     Ok(wordlist)
 }
 
-pub fn load_default_wordlist<R: Rng + Sized>(rng: &mut R) -> Vec<String> {
-    let mut wordlist = include_str!("./wordlist.txt")
+/// The default wordlist in its original order, for encodings that must map every word to the same number.
+pub fn default_words() -> Vec<&'static str> {
+    include_str!("./wordlist.txt")
         .split('\n')
-        .map(|value| value.to_owned())
         .filter(|value| !value.is_empty())
+        .collect()
+}
+
+pub fn load_default_wordlist<R: Rng + Sized>(rng: &mut R) -> Vec<String> {
+    let mut wordlist = default_words()
+        .into_iter()
+        .map(|value| value.to_owned())
         .collect::<Vec<_>>();
     let count = wordlist.len();
 
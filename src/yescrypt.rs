@@ -0,0 +1,368 @@
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::hashing::CRYPT_ALPHABET;
+
+/// The flavour of yescrypt that libxcrypt uses, and the only one that is implemented: read-write mode with six rounds of pwxform
+/// gathering four lanes of two 64-bit words from 12 KiB of S-boxes, which the reference implementation calls `YESCRYPT_DEFAULTS`.
+const FLAVOR: u32 = 47;
+
+/// The number of 64-bit words in each lane of pwxform.
+const PWX_SIMPLE: usize = 2;
+
+/// The number of lanes of pwxform, which are gathered from the S-boxes independently.
+const PWX_GATHER: usize = 4;
+
+/// The number of rounds of pwxform.
+const PWX_ROUNDS: usize = 6;
+
+/// The base-2 logarithm of the number of lanes in each S-box.
+const S_WIDTH: usize = 8;
+
+/// The number of 32-bit words in each of the three S-boxes.
+const S_WORDS: usize = (1 << S_WIDTH) * PWX_SIMPLE * 2;
+
+/// The bits of a 32-bit word that choose the byte offset of a lane within an S-box.
+const S_MASK: usize = ((1 << S_WIDTH) - 1) * PWX_SIMPLE * 8;
+
+/// The number of 32-bit words in a 64-byte Salsa20 block.
+const BLOCK_WORDS: usize = 16;
+
+/// The three S-boxes that pwxform reads from and writes to, which take turns in each role.
+struct Pwxform {
+    sboxes: Zeroizing<Vec<u32>>,
+    s0: usize,
+    s1: usize,
+    s2: usize,
+    w: usize
+}
+
+impl Pwxform {
+    fn word(&self, index: usize) -> u64 {
+        ((self.sboxes[index + 1] as u64) << 32) | self.sboxes[index] as u64
+    }
+
+    /// Transform a 64-byte block with multiplications and S-box lookups, which are cheap for a CPU but expensive for dedicated hardware.
+    fn transform(&mut self, block: &mut [u32]) {
+        for round in 0..PWX_ROUNDS {
+            for lane in (0..PWX_GATHER).map(|gather| gather * PWX_SIMPLE * 2) {
+                let p0 = self.s0 + (block[lane] as usize & S_MASK) / 4;
+                let p1 = self.s1 + (block[lane + 1] as usize & S_MASK) / 4;
+
+                for index in (0..PWX_SIMPLE).map(|simple| lane + simple * 2) {
+                    let product = (block[index + 1] as u64) * (block[index] as u64);
+                    let value = product.wrapping_add(self.word(p0 + index - lane)) ^ self.word(p1 + index - lane);
+
+                    block[index] = value as u32;
+                    block[index + 1] = (value >> 32) as u32;
+                }
+
+                if round != 0 && round != PWX_ROUNDS - 1 {
+                    for index in (0..PWX_SIMPLE).map(|simple| lane + simple * 2) {
+                        self.sboxes[self.s2 + self.w * 2] = block[index];
+                        self.sboxes[self.s2 + self.w * 2 + 1] = block[index + 1];
+                        self.w += 1;
+                    }
+                }
+            }
+        }
+
+        (self.s0, self.s1, self.s2) = (self.s2, self.s0, self.s1);
+        self.w &= (1 << S_WIDTH) * PWX_SIMPLE - 1;
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+
+    mac.update(message);
+
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+fn xor(target: &mut [u32], source: &[u32]) {
+    for (target, source) in target.iter_mut().zip(source) { *target ^= source; }
+}
+
+/// The Salsa20 core with the given number of rounds, on a block whose words are in the shuffled order that yescrypt keeps them in.
+fn salsa20(block: &mut [u32], rounds: usize) {
+    let mut x = Zeroizing::new([0_u32; BLOCK_WORDS]);
+
+    for index in 0..BLOCK_WORDS { x[index * 5 % BLOCK_WORDS] = block[index]; }
+
+    let mut quarter = |a: usize, b: usize, c: usize, d: usize| {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    };
+
+    for _ in 0..rounds / 2 {
+        quarter(0, 4, 8, 12);
+        quarter(5, 9, 13, 1);
+        quarter(10, 14, 2, 6);
+        quarter(15, 3, 7, 11);
+        quarter(0, 1, 2, 3);
+        quarter(5, 6, 7, 4);
+        quarter(10, 11, 8, 9);
+        quarter(15, 12, 13, 14);
+    }
+
+    for index in 0..BLOCK_WORDS { block[index] = block[index].wrapping_add(x[index * 5 % BLOCK_WORDS]); }
+}
+
+/// The BlockMix function of scrypt, which is only used to fill the S-boxes.
+fn blockmix_salsa8(block: &mut [u32], scratch: &mut [u32], r: usize) {
+    let mut x = Zeroizing::new([0_u32; BLOCK_WORDS]);
+
+    x.copy_from_slice(&block[(2 * r - 1) * BLOCK_WORDS..2 * r * BLOCK_WORDS]);
+
+    for index in 0..2 * r {
+        xor(x.as_mut_slice(), &block[index * BLOCK_WORDS..]);
+        salsa20(x.as_mut_slice(), 8);
+
+        // The even blocks go in the first half and the odd blocks in the second.
+        let destination = (index / 2 + (index % 2) * r) * BLOCK_WORDS;
+
+        scratch[destination..destination + BLOCK_WORDS].copy_from_slice(x.as_slice());
+    }
+
+    block[..2 * r * BLOCK_WORDS].copy_from_slice(&scratch[..2 * r * BLOCK_WORDS]);
+}
+
+/// The BlockMix function of yescrypt, which runs pwxform over every 64-byte block before a final round of Salsa20/2.
+fn blockmix_pwxform(block: &mut [u32], pwxform: &mut Pwxform, r: usize) {
+    let blocks = 2 * r;
+    let mut x = Zeroizing::new([0_u32; BLOCK_WORDS]);
+
+    x.copy_from_slice(&block[(blocks - 1) * BLOCK_WORDS..blocks * BLOCK_WORDS]);
+
+    for index in 0..blocks {
+        xor(x.as_mut_slice(), &block[index * BLOCK_WORDS..]);
+        pwxform.transform(x.as_mut_slice());
+        block[index * BLOCK_WORDS..(index + 1) * BLOCK_WORDS].copy_from_slice(x.as_slice());
+    }
+
+    salsa20(&mut block[(blocks - 1) * BLOCK_WORDS..], 2);
+}
+
+/// Read the last 64-byte block of `x` as an integer, as scrypt does to choose which earlier block to mix in.
+fn integerify(x: &[u32], r: usize) -> u64 {
+    let last = &x[(2 * r - 1) * BLOCK_WORDS..];
+
+    // The second word of the block is the 13th once it has been shuffled.
+    ((last[13] as u64) << 32) | last[0] as u64
+}
+
+/// The largest power of two that is no larger than `value`.
+fn p2floor(value: u64) -> u64 {
+    1 << value.ilog2()
+}
+
+/// Map `value` onto one of the blocks in the same power-of-two window as `index`, counting back from it.
+fn wrap(value: u64, index: u64) -> u64 {
+    let window = p2floor(index);
+
+    (value & (window - 1)) + (index - window)
+}
+
+/// Read bytes into words, shuffled into the order that yescrypt keeps them in.
+fn load(x: &mut [u32], bytes: &[u8]) {
+    for (block, words) in bytes.chunks_exact(BLOCK_WORDS * 4).zip(x.chunks_exact_mut(BLOCK_WORDS)) {
+        for (index, word) in words.iter_mut().enumerate() {
+            let offset = index * 5 % BLOCK_WORDS * 4;
+
+            *word = u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap());
+        }
+    }
+}
+
+/// Write shuffled words back out as bytes, undoing `load`.
+fn store(bytes: &mut [u8], x: &[u32]) {
+    for (block, words) in bytes.chunks_exact_mut(BLOCK_WORDS * 4).zip(x.chunks_exact(BLOCK_WORDS)) {
+        for (index, word) in words.iter().enumerate() {
+            let offset = index * 5 % BLOCK_WORDS * 4;
+
+            block[offset..offset + 4].copy_from_slice(&word.to_le_bytes());
+        }
+    }
+}
+
+/// Fill `v` with `n` blocks that each depend on the one before, and in read-write mode on a random earlier one too.
+fn smix1(bytes: &mut [u8], r: usize, n: usize, v: &mut [u32], scratch: &mut [u32], mut pwxform: Option<&mut Pwxform>) {
+    let size = 32 * r;
+    let (x, y) = scratch.split_at_mut(size);
+
+    load(x, bytes);
+
+    for index in 0..n {
+        v[index * size..(index + 1) * size].copy_from_slice(x);
+
+        if pwxform.is_some() && index > 1 {
+            let j = wrap(integerify(x, r), index as u64) as usize;
+
+            xor(x, &v[j * size..(j + 1) * size]);
+        }
+
+        match pwxform.as_deref_mut() {
+            Some(pwxform) => blockmix_pwxform(x, pwxform, r),
+            None => blockmix_salsa8(x, y, r)
+        }
+    }
+
+    store(bytes, x);
+}
+
+/// Mix `count` randomly chosen blocks of `v` back in, overwriting each one with the result.
+fn smix2(bytes: &mut [u8], r: usize, n: usize, count: usize, v: &mut [u32], scratch: &mut [u32], pwxform: &mut Pwxform) {
+    let size = 32 * r;
+    let x = &mut scratch[..size];
+
+    load(x, bytes);
+
+    for _ in 0..count {
+        let j = (integerify(x, r) & (n as u64 - 1)) as usize;
+
+        xor(x, &v[j * size..(j + 1) * size]);
+        v[j * size..(j + 1) * size].copy_from_slice(x);
+        blockmix_pwxform(x, pwxform, r);
+    }
+
+    store(bytes, x);
+}
+
+/// The sequential memory-hard function at the heart of yescrypt, which also updates the password that the final key is derived with.
+fn smix(bytes: &mut [u8], r: usize, n: usize, password: &mut [u8; 32]) {
+    let size = 32 * r;
+    let mut v = Zeroizing::new(vec![0_u32; size * n]);
+    let mut scratch = Zeroizing::new(vec![0_u32; 2 * size]);
+    let mut sboxes = Zeroizing::new(vec![0_u32; 3 * S_WORDS]);
+
+    smix1(&mut bytes[..128], 1, 3 * S_WORDS / 32, &mut sboxes, &mut scratch, None);
+
+    let mut pwxform = Pwxform { sboxes, s0: 2 * S_WORDS, s1: S_WORDS, s2: 0, w: 0 };
+
+    *password = *hmac_sha256(&bytes[bytes.len() - 64..], password);
+
+    // A third of the blocks are revisited, rounded up to an even number.
+    let count = (n.div_ceil(3) + 1) & !1;
+
+    smix1(bytes, r, n, &mut v, &mut scratch, Some(&mut pwxform));
+    smix2(bytes, r, p2floor(n as u64) as usize, count, &mut v, &mut scratch, &mut pwxform);
+}
+
+/// Derive a 32-byte key from a password, or a prehash of it that only uses a 64th of the memory.
+fn derive(password: &[u8], salt: &[u8], n: usize, r: usize, prehash: bool) -> Zeroizing<[u8; 32]> {
+    let key: &[u8] = if prehash { b"yescrypt-prehash" } else { b"yescrypt" };
+    let mut password = hmac_sha256(key, password);
+    let mut bytes = Zeroizing::new(vec![0_u8; 128 * r]);
+    let mut key = Zeroizing::new([0_u8; 32]);
+
+    pbkdf2_hmac::<Sha256>(password.as_slice(), salt, 1, &mut bytes);
+    password.copy_from_slice(&bytes[..32]);
+    smix(&mut bytes, r, n, &mut password);
+    pbkdf2_hmac::<Sha256>(password.as_slice(), &bytes, 1, key.as_mut_slice());
+
+    if prehash { return key; }
+
+    // The final steps are those of SCRAM, which derives a stored key from a client key.
+    let client = hmac_sha256(key.as_slice(), b"Client Key");
+
+    Zeroizing::new(Sha256::digest(client.as_slice()).into())
+}
+
+/// Write a number in the variable-length encoding of yescrypt's parameters, which uses one character for numbers up to 47.
+fn push_number(output: &mut String, value: u32, minimum: u32) {
+    let (mut start, mut end, mut characters, mut bits) = (0, 47, 1, 0);
+    let mut value = value - minimum;
+
+    loop {
+        let count = (end + 1 - start) << bits;
+
+        if value < count { break; }
+
+        value -= count;
+        start = end + 1;
+        end = start + (62 - end) / 2;
+        characters += 1;
+        bits += 6;
+    }
+
+    output.push(CRYPT_ALPHABET[(start + (value >> bits)) as usize] as char);
+
+    for _ in 1..characters {
+        bits -= 6;
+        output.push(CRYPT_ALPHABET[((value >> bits) & 0x3F) as usize] as char);
+    }
+}
+
+/// Write bytes in the little-endian Base64 of crypt(3), three at a time.
+fn push_bytes(output: &mut String, bytes: &[u8]) {
+    for group in bytes.chunks(3) {
+        let value = group.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u32);
+
+        for index in 0..(group.len() * 8).div_ceil(6) {
+            output.push(CRYPT_ALPHABET[((value >> (index * 6)) & 0x3F) as usize] as char);
+        }
+    }
+}
+
+/// Hash a password with yescrypt as a `$y$` string, exactly as libxcrypt does.
+///
+/// The cost is libxcrypt's, from 1 to 11, and each step doubles the memory used, from 1 MiB up to 1 GiB.
+pub fn yescrypt(password: &[u8], salt: &[u8], cost: u32) -> String {
+    let (log_n, r) = if cost <= 2 { (9 + cost, 8) } else { (7 + cost, 32) };
+    let n = 1_usize << log_n;
+
+    // Large parameters first hash the password with a 64th of the memory, so that an attacker cannot skip it with less.
+    let hash = if n * r as usize >= 0x20000 {
+        let prehash = derive(password, salt, n >> 6, r as usize, true);
+
+        derive(prehash.as_slice(), salt, n, r as usize, false)
+    } else {
+        derive(password, salt, n, r as usize, false)
+    };
+
+    let mut encoded = String::from("$y$");
+
+    push_number(&mut encoded, FLAVOR, 0);
+    push_number(&mut encoded, log_n, 1);
+    push_number(&mut encoded, r, 1);
+    encoded.push('$');
+    push_bytes(&mut encoded, salt);
+    encoded.push('$');
+    push_bytes(&mut encoded, hash.as_slice());
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_libxcrypt() {
+        // Calculated independently with libxcrypt, the second with its default cost, which prehashes the password.
+        assert_eq!(
+            yescrypt(b"Hello world!", b"saltsaltsaltsalt", 1),
+            "$y$j75$n34PoBLMgFrQVl4Rn34Po/$QozOSfAGIg8afGS9FxYfoXAmhedo2TTjpbA119L/DQ6"
+        );
+        assert_eq!(
+            yescrypt(b"", b"saltsaltsaltsalt", 5),
+            "$y$j9T$n34PoBLMgFrQVl4Rn34Po/$hYzjPJ8wnw5M5e2KyjP3N75BweFkmqofHNoTRXI09y7"
+        );
+    }
+
+    #[test]
+    fn encodes_large_parameters() {
+        let mut encoded = String::new();
+
+        push_number(&mut encoded, 47, 0);
+        push_number(&mut encoded, 48, 0);
+        push_number(&mut encoded, 100, 1);
+
+        assert_eq!(encoded, "jk.kn");
+    }
+}